@@ -0,0 +1,263 @@
+//! A scalable Bloom filter with `bfadd` / `bfexists` operations on the cache.
+
+use std::collections::hash_map::DefaultHasher;
+use std::f64::consts::LN_2;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use crate::collections::Collection;
+use crate::core::MiniCache;
+
+/// Capacity of the first layer of a default filter.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// False-positive rate of a default filter.
+const DEFAULT_ERROR_RATE: f64 = 0.01;
+
+/// Each new layer holds twice as many items as the previous one...
+const GROWTH: usize = 2;
+
+/// ...with half its false-positive rate, so the rates sum to at most the target.
+const TIGHTENING: f64 = 0.5;
+
+/// One fixed-size Bloom filter.
+#[derive(Debug, Clone, PartialEq)]
+struct Layer {
+    bits: Vec<u64>,
+    num_bits: u64,
+    hashes: u32,
+    capacity: usize,
+    len: usize,
+}
+
+impl Layer {
+    fn new(capacity: usize, error_rate: f64) -> Self {
+        let num_bits = (-(capacity as f64) * error_rate.ln() / (LN_2 * LN_2)).ceil() as u64;
+        let num_bits = num_bits.max(64);
+        let hashes = ((num_bits as f64 / capacity as f64) * LN_2)
+            .round()
+            .max(1.0) as u32;
+        Layer {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            hashes,
+            capacity,
+            len: 0,
+        }
+    }
+
+    /// Bit positions for an item, by double hashing.
+    fn positions(&self, (h1, h2): (u64, u64)) -> impl Iterator<Item = u64> + '_ {
+        (0..u64::from(self.hashes))
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    fn contains(&self, hashes: (u64, u64)) -> bool {
+        self.positions(hashes)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    fn insert(&mut self, hashes: (u64, u64)) {
+        let positions: Vec<u64> = self.positions(hashes).collect();
+        for bit in positions {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.len += 1;
+    }
+}
+
+/// A Bloom filter that grows as items are added.
+///
+/// A Bloom filter answers "have we seen this item?" in a fraction of the memory of an
+/// exact set: it never forgets an item it was given, but may claim to have seen an item
+/// it was not, at about the configured false-positive rate. When the current layer is
+/// full a larger one is added, so the filter stays within that rate however many items
+/// it receives.
+///
+/// Store it as the value of a `MiniCache` to use `bfadd` and `bfexists`. Filters
+/// created by `bfadd` use a capacity of 1024 and a 1% false-positive rate; to choose
+/// other parameters, `set` a filter built with [`BloomFilter::new`] first.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::BloomFilter;
+///
+/// let mut seen = BloomFilter::new(100, 0.001);
+/// assert!(seen.add("order-17"));
+/// assert!(!seen.add("order-17"));
+///
+/// assert!(seen.contains("order-17"));
+/// assert!(!seen.contains("order-18"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    layers: Vec<Layer>,
+    capacity: usize,
+    error_rate: f64,
+}
+
+impl Default for BloomFilter {
+    fn default() -> Self {
+        BloomFilter::new(DEFAULT_CAPACITY, DEFAULT_ERROR_RATE)
+    }
+}
+
+impl BloomFilter {
+    /// Creates a filter whose first layer holds `capacity` items at `error_rate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Items expected before the filter grows; at least 1
+    /// * `error_rate` - Target false-positive rate, at most 0.5
+    pub fn new(capacity: usize, error_rate: f64) -> Self {
+        BloomFilter {
+            layers: Vec::new(),
+            capacity: capacity.max(1),
+            error_rate: error_rate.clamp(f64::MIN_POSITIVE, 0.5),
+        }
+    }
+
+    /// Returns the number of items added.
+    pub fn len(&self) -> usize {
+        self.layers.iter().map(|layer| layer.len).sum()
+    }
+
+    /// Returns `true` if nothing has been added.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Adds `item`, returning `false` if the filter already (probably) contained it.
+    pub fn add<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        let hashes = Self::hashes(item);
+        if self.layers.iter().any(|layer| layer.contains(hashes)) {
+            return false;
+        }
+        let full = self
+            .layers
+            .last()
+            .is_none_or(|layer| layer.len >= layer.capacity);
+        if full {
+            let depth = self.layers.len() as u32;
+            let capacity = self.capacity.saturating_mul(GROWTH.saturating_pow(depth));
+            // Layer `i` gets `p * (1 - r) * r^i`, which sums to `p`
+            let error_rate = self.error_rate * (1.0 - TIGHTENING) * TIGHTENING.powi(depth as i32);
+            self.layers.push(Layer::new(capacity, error_rate));
+        }
+        if let Some(layer) = self.layers.last_mut() {
+            layer.insert(hashes);
+        }
+        true
+    }
+
+    /// Returns `true` if `item` was probably added, and `false` if it definitely was not.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        let hashes = Self::hashes(item);
+        self.layers.iter().any(|layer| layer.contains(hashes))
+    }
+
+    fn hashes<T: Hash + ?Sized>(item: &T) -> (u64, u64) {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let h1 = hasher.finish();
+        hasher.write_u64(h1);
+        // An odd step visits distinct positions for every power-of-two size
+        (h1, hasher.finish() | 1)
+    }
+}
+
+impl Collection for BloomFilter {
+    fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+impl<K> MiniCache<K, BloomFilter>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Adds `item` to the filter at `key`, returning `false` if it was probably there
+    /// already.
+    ///
+    /// A missing key is created with a default filter and `ttl`; an existing key keeps
+    /// its TTL. Like the other collection operations, this does not reach a backing
+    /// store.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{BloomFilter, MiniCache};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<&str, BloomFilter> = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     // Reserve a larger filter up front
+    ///     cache.set("seen", BloomFilter::new(1_000_000, 0.001), None).await;
+    ///
+    ///     assert!(cache.bfadd(&"seen", "user-42", None).await);
+    ///     assert!(!cache.bfadd(&"seen", "user-42", None).await);
+    ///     assert!(cache.bfexists(&"seen", "user-42").await);
+    ///     assert!(!cache.bfexists(&"seen", "user-43").await);
+    /// }
+    /// ```
+    pub async fn bfadd<T: Hash + ?Sized>(&self, key: &K, item: &T, ttl: Option<Duration>) -> bool {
+        self.mutate(key, Some(ttl), |filter| filter.add(item))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if `item` was probably added to the filter at `key`, and `false`
+    /// if it definitely was not or the key is missing.
+    pub async fn bfexists<T: Hash + ?Sized>(&self, key: &K, item: &T) -> bool {
+        self.inspect(key, |filter| filter.contains(item))
+            .await
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives_while_growing() {
+        let mut filter = BloomFilter::new(100, 0.01);
+        assert!(filter.is_empty());
+
+        for i in 0..10_000u32 {
+            filter.add(&i);
+        }
+        assert!((0..10_000u32).all(|i| filter.contains(&i)));
+        assert!(filter.layers.len() > 1);
+        assert!(filter.len() <= 10_000);
+    }
+
+    #[test]
+    fn test_false_positive_rate_stays_near_target() {
+        let mut filter = BloomFilter::new(1_000, 0.01);
+        for i in 0..20_000u32 {
+            filter.add(&i);
+        }
+
+        let false_positives = (20_000..120_000u32).filter(|i| filter.contains(i)).count();
+        assert!(false_positives < 2_000, "{false_positives} false positives");
+    }
+
+    #[tokio::test]
+    async fn test_cache_bloom_operations() {
+        let cache: MiniCache<&str, BloomFilter> = MiniCache::new(Duration::from_secs(1));
+
+        assert!(!cache.bfexists(&"ids", "a").await);
+        assert!(cache.bfadd(&"ids", "a", None).await);
+        assert!(!cache.bfadd(&"ids", "a", None).await);
+        assert!(cache.bfexists(&"ids", "a").await);
+        assert!(!cache.bfexists(&"ids", "b").await);
+
+        let filter = cache.get(&"ids").await.unwrap();
+        assert_eq!(filter.capacity, DEFAULT_CAPACITY);
+        assert_eq!(filter.len(), 1);
+    }
+}
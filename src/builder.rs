@@ -0,0 +1,288 @@
+//! Builder for configuring optional `MiniCache` features.
+
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+use crate::core::{IndexFactory, MiniCache, Tier, ordered_index};
+use crate::disk::{Codec, DiskTier};
+use crate::loader::{self, LoaderError, Refresh};
+use crate::store::{Backing, Store, WriteBehind};
+
+/// Largest [`Jitter::Percent`] accepted, so that a jittered TTL never drops to zero.
+const MAX_JITTER_PERCENT: f64 = 99.0;
+
+/// Random spread applied to TTLs by [`MiniCacheBuilder::ttl_jitter`].
+///
+/// The spread is symmetric, so the average TTL stays the one passed to `set`. A
+/// jittered TTL is never negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Jitter {
+    /// Spread by up to this percentage of the TTL in either direction. Clamped to
+    /// between 0 and 99.
+    Percent(f64),
+    /// Spread by up to this duration in either direction.
+    Absolute(Duration),
+}
+
+/// Configures and creates a [`MiniCache`].
+///
+/// Obtained from [`MiniCache::builder`]. Every setting is optional; a builder with no
+/// settings produces the same cache as [`MiniCache::new`].
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::MiniCache;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let cache: MiniCache<String, String> = MiniCache::builder(Duration::from_secs(60))
+///         .capacity(10_000)
+///         .build();
+///
+///     assert!(cache.is_empty().await);
+/// }
+/// ```
+pub struct MiniCacheBuilder<K, V> {
+    pub(crate) cleanup_interval: Duration,
+    pub(crate) capacity: Option<usize>,
+    pub(crate) index: Option<IndexFactory<K>>,
+    pub(crate) tier: Option<Tier<K, V>>,
+    pub(crate) backing: Option<Backing<K, V>>,
+    pub(crate) refresh: Option<Refresh<K, V>>,
+    pub(crate) early_expiration: Option<f64>,
+    pub(crate) jitter: Option<Jitter>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) clock: Arc<dyn Clock>,
+}
+
+impl<K, V> MiniCacheBuilder<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub(crate) fn new(cleanup_interval: Duration) -> Self {
+        MiniCacheBuilder {
+            cleanup_interval,
+            capacity: None,
+            index: None,
+            tier: None,
+            backing: None,
+            refresh: None,
+            early_expiration: None,
+            jitter: None,
+            seed: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Limits the number of entries kept in memory.
+    ///
+    /// When an insert would exceed the limit, the least recently used entry is evicted.
+    /// Evicted entries are dropped, or spilled to the disk tier if one is configured.
+    pub fn capacity(mut self, max_entries: usize) -> Self {
+        self.capacity = Some(max_entries);
+        self
+    }
+
+    /// Keeps the keys in a sorted index next to the hash map.
+    ///
+    /// Ordered queries such as [`MiniCache::range`], [`MiniCache::first`] and
+    /// [`MiniCache::pop_first`] then walk only the keys they return instead of sorting
+    /// every key. Inserts and removals pay an extra `O(log n)` to keep the index up to
+    /// date.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::builder(Duration::from_secs(60)).ordered().build();
+    ///
+    ///     cache.set(20, "b", None).await;
+    ///     cache.set(10, "a", None).await;
+    ///     cache.set(30, "c", None).await;
+    ///
+    ///     assert_eq!(cache.range(10..30).await, vec![(10, "a"), (20, "b")]);
+    /// }
+    /// ```
+    pub fn ordered(mut self) -> Self
+    where
+        K: Ord,
+    {
+        self.index = Some(ordered_index::<K>);
+        self
+    }
+
+    /// Adds a disk tier that receives entries evicted from memory.
+    ///
+    /// A `get` that misses in memory checks the disk tier and promotes a hit back into
+    /// memory. TTLs are kept when entries move between tiers, and the cleanup task purges
+    /// expired entries from both. The tier only receives entries once a
+    /// [`capacity`](Self::capacity) is set.
+    ///
+    /// Spilling only queues the entry for the tier's writer thread, and promotion reads
+    /// the file without holding the cache lock. [`MiniCache::flush`] waits for queued
+    /// entries to be written and reports a failed write.
+    ///
+    /// [`DiskTier::open`] deletes any segment files already in its directory, so
+    /// nothing from a previous run is served.
+    pub fn disk_tier(mut self, tier: DiskTier<K>) -> Self
+    where
+        V: Codec,
+    {
+        self.tier = Some(Arc::new(tier));
+        self
+    }
+
+    /// Attaches a backing store in write-through mode.
+    ///
+    /// `set` and `remove` write to the store before returning, and a `get` that misses
+    /// the cache loads the value from the store.
+    pub fn write_through<S>(mut self, store: S) -> Self
+    where
+        S: Store<K, V> + 'static,
+    {
+        self.backing = Some(Backing::write_through(Arc::new(store)));
+        self
+    }
+
+    /// Attaches a backing store in write-behind mode.
+    ///
+    /// `set` and `remove` only queue the write; queued writes are coalesced per key and
+    /// flushed in batches by a background task. Call [`MiniCache::shutdown`] before
+    /// exiting to flush whatever is still pending.
+    pub fn write_behind<S>(mut self, store: S, config: WriteBehind) -> Self
+    where
+        S: Store<K, V> + 'static,
+    {
+        self.backing = Some(Backing::write_behind(Arc::new(store), config));
+        self
+    }
+
+    /// Enables refresh-ahead: entries are reloaded in the background once they are
+    /// older than `refresh_after`.
+    ///
+    /// A `get` that hits an entry past its refresh point returns the current value
+    /// immediately and starts one background call to `loader`. On success the entry is
+    /// replaced with a fresh value and its original TTL; on failure the old value keeps
+    /// being served until it expires. Choose a `refresh_after` shorter than the TTLs you
+    /// use, so hot entries are reloaded before they expire.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{LoaderError, MiniCache};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::builder(Duration::from_secs(60))
+    ///         .refresh_ahead(Duration::from_secs(50), |key: u64| async move {
+    ///             Ok::<_, LoaderError>(format!("profile-{key}"))
+    ///         })
+    ///         .build();
+    ///
+    ///     cache.set(1, "profile-1".to_string(), Some(Duration::from_secs(60))).await;
+    ///     assert_eq!(cache.get(&1).await, Some("profile-1".to_string()));
+    /// }
+    /// ```
+    pub fn refresh_ahead<F, Fut, E>(mut self, refresh_after: Duration, loader: F) -> Self
+    where
+        F: Fn(K) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<V, E>> + Send + 'static,
+        E: Into<LoaderError>,
+    {
+        self.refresh = Some(Refresh {
+            after: refresh_after,
+            loader: loader::boxed(loader),
+        });
+        self
+    }
+
+    /// Enables probabilistic early expiration (XFetch) to spread out recomputation.
+    ///
+    /// A `get` may treat an entry as already expired shortly before its deadline, with a
+    /// probability that rises as the deadline approaches and with the time the value
+    /// took to compute. The caller that sees the miss recomputes the value while other
+    /// readers keep being served the cached one. The stored deadline stays the hard
+    /// limit.
+    ///
+    /// Recompute cost is recorded for values produced by [`MiniCache::get_with`] and by
+    /// background reloads, and can be passed to [`MiniCache::set_with_cost`]; entries
+    /// written with plain `set` have no recorded cost and only expire at their
+    /// deadline. `beta` scales how early expiry happens; `1.0` is the usual choice,
+    /// larger values expire earlier.
+    pub fn early_expiration(mut self, beta: f64) -> Self {
+        self.early_expiration = Some(beta.max(0.0));
+        self
+    }
+
+    /// Randomly spreads the TTLs passed to `set` so entries written together do not all
+    /// expire in the same cleanup tick.
+    ///
+    /// A [`Jitter::Percent`] is clamped to between 0 and 99 so that no TTL is spread
+    /// down to zero, and a NaN percentage leaves TTLs as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{Jitter, MiniCache};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // A 60 second TTL becomes anything between 54 and 66 seconds
+    ///     let cache = MiniCache::builder(Duration::from_secs(1))
+    ///         .ttl_jitter(Jitter::Percent(10.0))
+    ///         .seed(42)
+    ///         .build();
+    ///
+    ///     cache.set("key1", "value1", Some(Duration::from_secs(60))).await;
+    /// }
+    /// ```
+    pub fn ttl_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = match jitter {
+            Jitter::Percent(percent) if percent.is_nan() => None,
+            Jitter::Percent(percent) => {
+                Some(Jitter::Percent(percent.clamp(0.0, MAX_JITTER_PERCENT)))
+            }
+            absolute => Some(absolute),
+        };
+        self
+    }
+
+    /// Seeds the random number generator used for TTL jitter and early expiration.
+    ///
+    /// Caches built with the same seed make the same random choices for the same
+    /// sequence of operations, which keeps tests deterministic. Without a seed the
+    /// generator is seeded randomly.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets the clock used for every TTL calculation. Defaults to [`SystemClock`].
+    ///
+    /// Pass a [`MockClock`](crate::MockClock) to test expiry deterministically without
+    /// sleeping.
+    pub fn clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Creates the cache and starts its background tasks.
+    pub fn build(self) -> MiniCache<K, V> {
+        MiniCache::from_builder(self)
+    }
+}
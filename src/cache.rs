@@ -0,0 +1,391 @@
+//! The common async `Cache` trait together with the `NoopCache` and `TieredCache` implementations.
+
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::core::MiniCache;
+
+/// TTL of entries promoted into `L1` unless [`TieredCache::promote_ttl`] sets another,
+/// which bounds how long `L1` can serve a value `L2` has since expired or replaced.
+pub const DEFAULT_PROMOTE_TTL: Duration = Duration::from_secs(60);
+
+/// The async key-value surface shared by every cache implementation in this crate.
+///
+/// Code that only needs to read and write cached values can take `impl Cache<K, V>`
+/// (or `Arc<dyn Cache<K, V>>`) instead of a concrete `MiniCache`, which makes it easy
+/// to substitute a [`NoopCache`], a layered [`TieredCache`] or a test double.
+///
+/// The trait is object safe, so implementations can be chosen at runtime.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{Cache, MiniCache, NoopCache};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// async fn remember(cache: &dyn Cache<String, String>) -> Option<String> {
+///     cache.set("greeting".to_string(), "hello".to_string(), None).await;
+///     cache.get(&"greeting".to_string()).await
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let enabled: Arc<dyn Cache<String, String>> = Arc::new(MiniCache::new(Duration::from_secs(60)));
+///     let disabled: Arc<dyn Cache<String, String>> = Arc::new(NoopCache);
+///
+///     assert_eq!(remember(enabled.as_ref()).await, Some("hello".to_string()));
+///     assert_eq!(remember(disabled.as_ref()).await, None);
+/// }
+/// ```
+#[async_trait]
+pub trait Cache<K, V>: Send + Sync
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+{
+    /// Stores a key-value pair with an optional TTL, overwriting any existing entry.
+    async fn set(&self, key: K, value: V, ttl: Option<Duration>);
+
+    /// Returns the value for `key` if it exists and has not expired.
+    async fn get(&self, key: &K) -> Option<V>;
+
+    /// Removes `key` from the cache.
+    async fn remove(&self, key: &K);
+
+    /// Removes every entry from the cache.
+    async fn clear(&self);
+
+    /// Returns `true` if `key` exists and has not expired.
+    async fn contains(&self, key: &K) -> bool {
+        self.get(key).await.is_some()
+    }
+
+    /// Returns the number of valid (non-expired) entries.
+    async fn len(&self) -> usize;
+
+    /// Returns `true` if the cache holds no valid (non-expired) entries.
+    async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Returns all valid (non-expired) keys in no particular order.
+    async fn keys(&self) -> Vec<K>;
+}
+
+#[async_trait]
+impl<K, V> Cache<K, V> for MiniCache<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    async fn set(&self, key: K, value: V, ttl: Option<Duration>) {
+        MiniCache::set(self, key, value, ttl).await
+    }
+
+    async fn get(&self, key: &K) -> Option<V> {
+        MiniCache::get(self, key).await
+    }
+
+    async fn remove(&self, key: &K) {
+        MiniCache::remove(self, key).await
+    }
+
+    async fn clear(&self) {
+        MiniCache::clear(self).await
+    }
+
+    async fn contains(&self, key: &K) -> bool {
+        MiniCache::contains(self, key).await
+    }
+
+    async fn len(&self) -> usize {
+        MiniCache::len(self).await
+    }
+
+    async fn is_empty(&self) -> bool {
+        MiniCache::is_empty(self).await
+    }
+
+    async fn keys(&self) -> Vec<K> {
+        MiniCache::keys(self).await
+    }
+}
+
+/// A cache that never stores anything.
+///
+/// Every `get` is a miss and every `set` is discarded. Useful for switching caching
+/// off through configuration without changing the calling code.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{Cache, NoopCache};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let cache: &dyn Cache<&str, &str> = &NoopCache;
+///
+///     cache.set("key1", "value1", None).await;
+///     assert_eq!(cache.get(&"key1").await, None);
+///     assert!(cache.is_empty().await);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopCache;
+
+#[async_trait]
+impl<K, V> Cache<K, V> for NoopCache
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+{
+    async fn set(&self, _key: K, _value: V, _ttl: Option<Duration>) {}
+
+    async fn get(&self, _key: &K) -> Option<V> {
+        None
+    }
+
+    async fn remove(&self, _key: &K) {}
+
+    async fn clear(&self) {}
+
+    async fn len(&self) -> usize {
+        0
+    }
+
+    async fn keys(&self) -> Vec<K> {
+        Vec::new()
+    }
+}
+
+/// A two-level cache that layers a fast `L1` cache in front of a larger `L2` cache.
+///
+/// Writes go to both tiers. Reads try `L1` first and fall back to `L2`; a value found
+/// only in `L2` is promoted into `L1` so the next read is served by the fast tier.
+/// Promoted entries are stored in `L1` with the TTL configured by
+/// [`TieredCache::promote_ttl`], [`DEFAULT_PROMOTE_TTL`] by default. `L1` cannot see
+/// the remaining TTL of the `L2` entry, so a promoted value may outlive it there by up
+/// to that TTL.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{Cache, MiniCache, TieredCache};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let l1 = MiniCache::new(Duration::from_secs(1));
+///     let l2 = MiniCache::new(Duration::from_secs(60));
+///     let cache = TieredCache::new(l1.clone(), l2.clone())
+///         .promote_ttl(Duration::from_secs(30));
+///
+///     l2.set("key1", "value1", None).await;
+///
+///     // Served by L2 and promoted into L1
+///     assert_eq!(cache.get(&"key1").await, Some("value1"));
+///     assert_eq!(l1.get(&"key1").await, Some("value1"));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TieredCache<L1, L2> {
+    l1: L1,
+    l2: L2,
+    promote_ttl: Duration,
+}
+
+impl<L1, L2> TieredCache<L1, L2> {
+    /// Creates a tiered cache from a fast `l1` and a larger `l2` cache.
+    pub fn new(l1: L1, l2: L2) -> Self {
+        TieredCache {
+            l1,
+            l2,
+            promote_ttl: DEFAULT_PROMOTE_TTL,
+        }
+    }
+
+    /// Sets the TTL given to entries promoted from `L2` into `L1`.
+    ///
+    /// This is the longest `L1` keeps serving a promoted value after `L2` has expired or
+    /// replaced it.
+    pub fn promote_ttl(mut self, ttl: Duration) -> Self {
+        self.promote_ttl = ttl;
+        self
+    }
+
+    /// Returns a reference to the first-level cache.
+    pub fn l1(&self) -> &L1 {
+        &self.l1
+    }
+
+    /// Returns a reference to the second-level cache.
+    pub fn l2(&self) -> &L2 {
+        &self.l2
+    }
+}
+
+#[async_trait]
+impl<K, V, L1, L2> Cache<K, V> for TieredCache<L1, L2>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    L1: Cache<K, V>,
+    L2: Cache<K, V>,
+{
+    async fn set(&self, key: K, value: V, ttl: Option<Duration>) {
+        self.l2.set(key.clone(), value.clone(), ttl).await;
+        self.l1.set(key, value, ttl).await;
+    }
+
+    async fn get(&self, key: &K) -> Option<V> {
+        if let Some(value) = self.l1.get(key).await {
+            return Some(value);
+        }
+        let value = self.l2.get(key).await?;
+        self.l1
+            .set(key.clone(), value.clone(), Some(self.promote_ttl))
+            .await;
+        Some(value)
+    }
+
+    async fn remove(&self, key: &K) {
+        self.l1.remove(key).await;
+        self.l2.remove(key).await;
+    }
+
+    async fn clear(&self) {
+        self.l1.clear().await;
+        self.l2.clear().await;
+    }
+
+    async fn contains(&self, key: &K) -> bool {
+        self.l1.contains(key).await || self.l2.contains(key).await
+    }
+
+    async fn len(&self) -> usize {
+        self.keys().await.len()
+    }
+
+    async fn is_empty(&self) -> bool {
+        self.l1.is_empty().await && self.l2.is_empty().await
+    }
+
+    async fn keys(&self) -> Vec<K> {
+        let mut seen = HashSet::new();
+        let mut keys = self.l1.keys().await;
+        keys.extend(self.l2.keys().await);
+        keys.retain(|k| seen.insert(k.clone()));
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_minicache_through_trait_object() {
+        let cache: Arc<dyn Cache<&str, &str>> = Arc::new(MiniCache::new(Duration::from_secs(1)));
+
+        cache.set("key1", "value1", None).await;
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+        assert!(cache.contains(&"key1").await);
+        assert_eq!(cache.len().await, 1);
+
+        cache.remove(&"key1").await;
+        assert!(cache.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_noop_cache_stores_nothing() {
+        let cache: &dyn Cache<&str, &str> = &NoopCache;
+
+        cache.set("key1", "value1", None).await;
+
+        assert_eq!(cache.get(&"key1").await, None);
+        assert!(!cache.contains(&"key1").await);
+        assert_eq!(cache.len().await, 0);
+        assert!(cache.keys().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tiered_set_writes_both_tiers() {
+        let l1 = MiniCache::new(Duration::from_secs(1));
+        let l2 = MiniCache::new(Duration::from_secs(1));
+        let cache = TieredCache::new(l1.clone(), l2.clone());
+
+        cache.set("key1", "value1", None).await;
+
+        assert_eq!(l1.get(&"key1").await, Some("value1"));
+        assert_eq!(l2.get(&"key1").await, Some("value1"));
+    }
+
+    #[tokio::test]
+    async fn test_tiered_get_promotes_from_l2() {
+        let l1 = MiniCache::new(Duration::from_secs(1));
+        let l2 = MiniCache::new(Duration::from_secs(1));
+        let cache = TieredCache::new(l1.clone(), l2.clone());
+
+        l2.set("key1", "value1", None).await;
+        assert_eq!(l1.get(&"key1").await, None);
+
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+        assert_eq!(l1.get(&"key1").await, Some("value1"));
+    }
+
+    #[tokio::test]
+    async fn test_tiered_remove_clear_and_keys() {
+        let l1 = MiniCache::new(Duration::from_secs(1));
+        let l2 = MiniCache::new(Duration::from_secs(1));
+        let cache = TieredCache::new(l1.clone(), l2.clone());
+
+        cache.set("key1", "value1", None).await;
+        l2.set("key2", "value2", None).await;
+
+        let mut keys = cache.keys().await;
+        keys.sort();
+        assert_eq!(keys, vec!["key1", "key2"]);
+        assert_eq!(cache.len().await, 2);
+
+        cache.remove(&"key1").await;
+        assert!(!cache.contains(&"key1").await);
+        assert!(cache.contains(&"key2").await);
+
+        cache.clear().await;
+        assert!(cache.is_empty().await);
+        assert!(l2.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_promoted_entries_expire_from_l1() {
+        let clock = MockClock::new();
+        let build = || {
+            MiniCache::builder(Duration::from_secs(1))
+                .clock(clock.clone())
+                .build()
+        };
+        let (l1, l2) = (build(), build());
+        let cache = TieredCache::new(l1.clone(), l2.clone()).promote_ttl(Duration::from_secs(5));
+
+        l2.set("key1", "value1", Some(Duration::from_secs(3))).await;
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(l2.get(&"key1").await, None);
+        assert_eq!(cache.get(&"key1").await, None);
+
+        // Without a promote TTL, L1 still lets go of a value L2 has replaced
+        let cache = TieredCache::new(l1.clone(), l2.clone());
+        l2.set("key2", "old", None).await;
+        assert_eq!(cache.get(&"key2").await, Some("old"));
+        l2.set("key2", "new", None).await;
+
+        clock.advance(DEFAULT_PROMOTE_TTL);
+        assert_eq!(cache.get(&"key2").await, Some("new"));
+    }
+}
@@ -0,0 +1,122 @@
+//! Time sources used for expiry, so TTL behavior can be tested without real sleeps.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time for TTL calculations.
+///
+/// Every expiry decision a `MiniCache` makes, in `set`, `get`, `len`, `keys` and the
+/// background cleanup task, reads the time through its clock. The default is
+/// [`SystemClock`]; tests can install a [`MockClock`] with
+/// [`MiniCacheBuilder::clock`](crate::MiniCacheBuilder::clock) and move time forward by hand.
+pub trait Clock: Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock, backed by [`Instant::now`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so a test can keep one handle and pass another to the
+/// cache.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{MiniCache, MockClock};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let clock = MockClock::new();
+///     let cache = MiniCache::builder(Duration::from_secs(60))
+///         .clock(clock.clone())
+///         .build();
+///
+///     cache.set("key1", "value1", Some(Duration::from_secs(30))).await;
+///
+///     clock.advance(Duration::from_secs(29));
+///     assert_eq!(cache.get(&"key1").await, Some("value1"));
+///
+///     clock.advance(Duration::from_secs(1));
+///     assert_eq!(cache.get(&"key1").await, None);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Creates a mock clock that starts at the current real time.
+    pub fn new() -> Self {
+        MockClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.lock() += duration;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Instant> {
+        self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MockClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockClock")
+            .field("now", &*self.lock())
+            .finish()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new();
+        let start = clock.now();
+
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now(), start + Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_mock_clock_clones_share_time() {
+        let clock = MockClock::new();
+        let handle = clock.clone();
+
+        handle.advance(Duration::from_millis(250));
+
+        assert_eq!(clock.now(), handle.now());
+    }
+}
@@ -0,0 +1,396 @@
+//! Redis-style list, set and hash operations on caches whose values are collections.
+//!
+//! The operations mutate the stored collection in place under the cache lock, so they
+//! are atomic per key and never clone the whole collection to change one element. The
+//! TTL applies to the key as a whole: it is set when an operation creates the key and
+//! kept by later operations. A collection that becomes empty is removed. Like the
+//! counters, these operations live in the cache only and do not reach a backing store.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::core::MiniCache;
+
+/// A value that can be created empty and is removed from the cache once it is empty
+/// again.
+pub(crate) trait Collection: Default {
+    fn is_empty(&self) -> bool;
+}
+
+impl<T> Collection for VecDeque<T> {
+    fn is_empty(&self) -> bool {
+        VecDeque::is_empty(self)
+    }
+}
+
+impl<T, S: Default> Collection for HashSet<T, S> {
+    fn is_empty(&self) -> bool {
+        HashSet::is_empty(self)
+    }
+}
+
+impl<F, T, S: Default> Collection for HashMap<F, T, S> {
+    fn is_empty(&self) -> bool {
+        HashMap::is_empty(self)
+    }
+}
+
+/// Resolves a Redis-style index, where negative values count from the end.
+fn resolve(index: isize, len: usize) -> isize {
+    if index < 0 {
+        len as isize + index
+    } else {
+        index
+    }
+}
+
+impl<K, T> MiniCache<K, VecDeque<T>>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    /// Pushes `values` onto the front of the list at `key`, one after the other, and
+    /// returns the new length (`LPUSH`).
+    ///
+    /// A missing key is created with `ttl`; an existing key keeps its TTL.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::collections::VecDeque;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<&str, VecDeque<u32>> = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.lpush(&"recent", [1, 2], None).await;
+    ///     cache.rpush(&"recent", [3], None).await;
+    ///
+    ///     assert_eq!(cache.lrange(&"recent", 0, -1).await, vec![2, 1, 3]);
+    ///     assert_eq!(cache.lpop(&"recent").await, Some(2));
+    /// }
+    /// ```
+    pub async fn lpush<I>(&self, key: &K, values: I, ttl: Option<Duration>) -> usize
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.mutate(key, Some(ttl), |list| {
+            for value in values {
+                list.push_front(value);
+            }
+            list.len()
+        })
+        .await
+        .unwrap_or(0)
+    }
+
+    /// Pushes `values` onto the back of the list at `key` and returns the new length
+    /// (`RPUSH`).
+    ///
+    /// A missing key is created with `ttl`; an existing key keeps its TTL.
+    pub async fn rpush<I>(&self, key: &K, values: I, ttl: Option<Duration>) -> usize
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.mutate(key, Some(ttl), |list| {
+            list.extend(values);
+            list.len()
+        })
+        .await
+        .unwrap_or(0)
+    }
+
+    /// Removes and returns the first element of the list at `key` (`LPOP`).
+    pub async fn lpop(&self, key: &K) -> Option<T> {
+        self.mutate(key, None, VecDeque::pop_front).await.flatten()
+    }
+
+    /// Removes and returns the last element of the list at `key` (`RPOP`).
+    pub async fn rpop(&self, key: &K) -> Option<T> {
+        self.mutate(key, None, VecDeque::pop_back).await.flatten()
+    }
+
+    /// Returns the elements of the list at `key` from `start` to `stop`, both inclusive
+    /// (`LRANGE`).
+    ///
+    /// Negative indices count from the end, so `0, -1` returns the whole list. Indices
+    /// past either end are clamped.
+    pub async fn lrange(&self, key: &K, start: isize, stop: isize) -> Vec<T> {
+        self.inspect(key, |list| {
+            let start = resolve(start, list.len()).max(0);
+            let stop = resolve(stop, list.len()).min(list.len() as isize - 1);
+            if start > stop {
+                return Vec::new();
+            }
+            list.range(start as usize..=stop as usize)
+                .cloned()
+                .collect()
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Returns the length of the list at `key`, or zero if it does not exist (`LLEN`).
+    pub async fn llen(&self, key: &K) -> usize {
+        self.inspect(key, VecDeque::len).await.unwrap_or(0)
+    }
+}
+
+impl<K, T> MiniCache<K, HashSet<T>>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    T: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Adds `members` to the set at `key` and returns how many were not already in it
+    /// (`SADD`).
+    ///
+    /// A missing key is created with `ttl`; an existing key keeps its TTL.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::collections::HashSet;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<&str, HashSet<&str>> = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     assert_eq!(cache.sadd(&"online", ["alice", "bob"], None).await, 2);
+    ///     assert_eq!(cache.sadd(&"online", ["bob"], None).await, 0);
+    ///     assert!(cache.sismember(&"online", &"alice").await);
+    ///
+    ///     cache.srem(&"online", [&"alice"]).await;
+    ///     assert_eq!(cache.scard(&"online").await, 1);
+    /// }
+    /// ```
+    pub async fn sadd<I>(&self, key: &K, members: I, ttl: Option<Duration>) -> usize
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.mutate(key, Some(ttl), |set| {
+            members
+                .into_iter()
+                .filter(|member| set.insert(member.clone()))
+                .count()
+        })
+        .await
+        .unwrap_or(0)
+    }
+
+    /// Removes `members` from the set at `key` and returns how many were in it
+    /// (`SREM`).
+    pub async fn srem<'a, I>(&self, key: &K, members: I) -> usize
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: 'a,
+    {
+        self.mutate(key, None, |set| {
+            members
+                .into_iter()
+                .filter(|member| set.remove(*member))
+                .count()
+        })
+        .await
+        .unwrap_or(0)
+    }
+
+    /// Returns `true` if `member` is in the set at `key` (`SISMEMBER`).
+    pub async fn sismember(&self, key: &K, member: &T) -> bool {
+        self.inspect(key, |set| set.contains(member))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Returns all members of the set at `key` (`SMEMBERS`).
+    pub async fn smembers(&self, key: &K) -> HashSet<T> {
+        self.inspect(key, HashSet::clone).await.unwrap_or_default()
+    }
+
+    /// Returns the number of members in the set at `key` (`SCARD`).
+    pub async fn scard(&self, key: &K) -> usize {
+        self.inspect(key, HashSet::len).await.unwrap_or(0)
+    }
+}
+
+impl<K, F, T> MiniCache<K, HashMap<F, T>>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    F: Hash + Eq + Clone + Send + Sync + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    /// Sets `field` of the hash at `key` to `value` and returns the previous value of
+    /// the field (`HSET`).
+    ///
+    /// A missing key is created with `ttl`; an existing key keeps its TTL.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<u64, HashMap<&str, String>> =
+    ///         MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.hset(&7, "name", "alice".to_string(), None).await;
+    ///     cache.hset(&7, "email", "alice@example.com".to_string(), None).await;
+    ///
+    ///     assert_eq!(cache.hget(&7, &"name").await, Some("alice".to_string()));
+    ///     assert!(cache.hdel(&7, &"email").await);
+    ///     assert_eq!(cache.hlen(&7).await, 1);
+    /// }
+    /// ```
+    pub async fn hset(&self, key: &K, field: F, value: T, ttl: Option<Duration>) -> Option<T> {
+        self.mutate(key, Some(ttl), |hash| hash.insert(field, value))
+            .await
+            .flatten()
+    }
+
+    /// Returns the value of `field` in the hash at `key` (`HGET`).
+    pub async fn hget(&self, key: &K, field: &F) -> Option<T> {
+        self.inspect(key, |hash| hash.get(field).cloned())
+            .await
+            .flatten()
+    }
+
+    /// Removes `field` from the hash at `key` and returns `true` if it was there
+    /// (`HDEL`).
+    pub async fn hdel(&self, key: &K, field: &F) -> bool {
+        self.mutate(key, None, |hash| hash.remove(field).is_some())
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Returns every field and value of the hash at `key` (`HGETALL`).
+    pub async fn hgetall(&self, key: &K) -> HashMap<F, T> {
+        self.inspect(key, HashMap::clone).await.unwrap_or_default()
+    }
+
+    /// Returns the number of fields in the hash at `key` (`HLEN`).
+    pub async fn hlen(&self, key: &K) -> usize {
+        self.inspect(key, HashMap::len).await.unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[tokio::test]
+    async fn test_list_operations() {
+        let cache: MiniCache<&str, VecDeque<i32>> = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(cache.lpush(&"list", [1, 2], None).await, 2);
+        assert_eq!(cache.rpush(&"list", [3, 4], None).await, 4);
+        assert_eq!(cache.lrange(&"list", 0, -1).await, vec![2, 1, 3, 4]);
+        assert_eq!(cache.lrange(&"list", 1, 2).await, vec![1, 3]);
+        assert_eq!(cache.lrange(&"list", -2, 100).await, vec![3, 4]);
+        assert!(cache.lrange(&"list", 3, 1).await.is_empty());
+        assert_eq!(cache.llen(&"list").await, 4);
+
+        assert_eq!(cache.lpop(&"list").await, Some(2));
+        assert_eq!(cache.rpop(&"list").await, Some(4));
+        assert_eq!(cache.get(&"list").await, Some(VecDeque::from([1, 3])));
+    }
+
+    #[tokio::test]
+    async fn test_empty_collections_are_removed() {
+        let cache: MiniCache<&str, VecDeque<i32>> = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(cache.lpop(&"list").await, None);
+        assert!(!cache.contains(&"list").await);
+
+        cache.rpush(&"list", [1], None).await;
+        cache.lpop(&"list").await;
+        assert!(!cache.contains(&"list").await);
+
+        cache.rpush(&"list", [], None).await;
+        assert!(cache.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_set_operations() {
+        let cache: MiniCache<&str, HashSet<&str>> = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(cache.sadd(&"tags", ["a", "b", "a"], None).await, 2);
+        assert_eq!(cache.sadd(&"tags", ["b", "c"], None).await, 1);
+        assert!(cache.sismember(&"tags", &"c").await);
+        assert!(!cache.sismember(&"tags", &"d").await);
+        assert_eq!(cache.scard(&"tags").await, 3);
+
+        assert_eq!(cache.srem(&"tags", [&"a", &"d"]).await, 1);
+        assert_eq!(cache.smembers(&"tags").await, HashSet::from(["b", "c"]));
+        assert_eq!(cache.srem(&"tags", [&"b", &"c"]).await, 2);
+        assert!(!cache.contains(&"tags").await);
+    }
+
+    #[tokio::test]
+    async fn test_hash_operations() {
+        let cache: MiniCache<&str, HashMap<&str, i32>> = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(cache.hset(&"user", "age", 30, None).await, None);
+        assert_eq!(cache.hset(&"user", "age", 31, None).await, Some(30));
+        cache.hset(&"user", "score", 7, None).await;
+
+        assert_eq!(cache.hget(&"user", &"age").await, Some(31));
+        assert_eq!(cache.hget(&"user", &"missing").await, None);
+        assert_eq!(cache.hlen(&"user").await, 2);
+        assert_eq!(
+            cache.hgetall(&"user").await,
+            HashMap::from([("age", 31), ("score", 7)])
+        );
+
+        assert!(cache.hdel(&"user", &"age").await);
+        assert!(!cache.hdel(&"user", &"age").await);
+        assert!(cache.hdel(&"user", &"score").await);
+        assert!(!cache.contains(&"user").await);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_applies_to_whole_key() {
+        let clock = MockClock::new();
+        let cache: MiniCache<&str, HashSet<u32>> = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+
+        cache.sadd(&"set", [1], Some(Duration::from_secs(10))).await;
+        clock.advance(Duration::from_secs(6));
+        // Later writes keep the original deadline
+        cache.sadd(&"set", [2], Some(Duration::from_secs(60))).await;
+        assert_eq!(cache.scard(&"set").await, 2);
+
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(cache.scard(&"set").await, 0);
+        assert!(!cache.sismember(&"set", &1).await);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_pushes_are_not_lost() {
+        let cache: MiniCache<&str, VecDeque<u32>> = MiniCache::new(Duration::from_secs(1));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|i| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    for j in 0..20 {
+                        cache.rpush(&"list", [i * 100 + j], None).await;
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(cache.llen(&"list").await, 200);
+    }
+}
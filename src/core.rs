@@ -1,13 +1,435 @@
 //! Core implementation of MiniCache - an async-compatible in-memory cache with TTL support.
 
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock, RwLockWriteGuard};
 use tokio::time::interval;
 
+use crate::builder::{Jitter, MiniCacheBuilder};
+use crate::clock::Clock;
+use crate::collections::Collection;
+use crate::disk::Spill;
+use crate::iter::{Iter, Values};
+use crate::keylock::KeyLocks;
+use crate::loader::{self, Loader, LoaderError, Lookup, Refresh, StalePolicy};
+use crate::namespace::{Namespace, NamespaceOptions};
+use crate::pattern::glob_match;
+use crate::pubsub::{Broker, Subscription};
+use crate::rng::Rng;
+use crate::store::{Backing, StoreError};
+
 /// Type alias for the internal cache storage
-type CacheMap<K, V> = Arc<RwLock<HashMap<K, (V, Option<Instant>)>>>;
+type CacheMap<K, V> = Arc<RwLock<Storage<K, V>>>;
+
+/// Type alias for the optional second tier that receives evicted entries
+pub(crate) type Tier<K, V> = Arc<dyn Spill<K, V> + Send + Sync>;
+
+/// How often a read retries promoting a key that is spilled again while it is read.
+const PROMOTE_ATTEMPTS: usize = 3;
+
+/// Creates an empty sorted key index; stored by caches built with
+/// [`MiniCacheBuilder::ordered`] so namespaces can get an index of their own.
+pub(crate) type IndexFactory<K> = fn() -> Box<dyn KeyIndex<K>>;
+
+/// A sorted set of the keys held in memory, used for ordered queries.
+///
+/// Boxed so that `Storage` needs no `Ord` bound; only caches whose keys are `Ord` can
+/// create one.
+pub(crate) trait KeyIndex<K>: Send + Sync {
+    fn insert(&mut self, key: K);
+    fn remove(&mut self, key: &K);
+    fn clear(&mut self);
+    fn range<'a>(
+        &'a self,
+        lower: Bound<&K>,
+        upper: Bound<&K>,
+    ) -> Box<dyn DoubleEndedIterator<Item = &'a K> + 'a>;
+    /// The underlying set, for lookups by a borrowed form of the key such as a string
+    /// prefix.
+    fn as_set(&self) -> &BTreeSet<K>;
+}
+
+impl<K> KeyIndex<K> for BTreeSet<K>
+where
+    K: Ord + Send + Sync,
+{
+    fn insert(&mut self, key: K) {
+        BTreeSet::insert(self, key);
+    }
+
+    fn remove(&mut self, key: &K) {
+        BTreeSet::remove(self, key);
+    }
+
+    fn clear(&mut self) {
+        BTreeSet::clear(self);
+    }
+
+    fn range<'a>(
+        &'a self,
+        lower: Bound<&K>,
+        upper: Bound<&K>,
+    ) -> Box<dyn DoubleEndedIterator<Item = &'a K> + 'a> {
+        Box::new(BTreeSet::range(self, (lower, upper)))
+    }
+
+    fn as_set(&self) -> &BTreeSet<K> {
+        self
+    }
+}
+
+pub(crate) fn ordered_index<K>() -> Box<dyn KeyIndex<K>>
+where
+    K: Ord + Send + Sync + 'static,
+{
+    Box::new(BTreeSet::new())
+}
+
+/// A cached value together with its expiry deadline, the TTL it was written with,
+/// its refresh deadline and its recency stamp.
+///
+/// After `refresh_at` the entry is stale: it is still served until `expire_at`, but
+/// the next read triggers a background reload. `version` changes on every write, which
+/// is how a finished reload detects that the entry was overwritten in the meantime.
+/// `cost` is how long the loader took to compute the value, if it was loaded. `seq`
+/// is the key's position in the scan order and survives overwrites.
+struct Entry<V> {
+    value: V,
+    expire_at: Option<Instant>,
+    ttl: Option<Duration>,
+    cost: Duration,
+    refresh_at: Option<Instant>,
+    refresh_after: Option<Duration>,
+    refreshing: bool,
+    version: u64,
+    stamp: u64,
+    seq: u64,
+}
+
+impl<V> Entry<V> {
+    fn new(
+        value: V,
+        expire_at: Option<Instant>,
+        ttl: Option<Duration>,
+        refresh_after: Option<Duration>,
+        now: Instant,
+    ) -> Self {
+        Entry {
+            value,
+            expire_at,
+            ttl,
+            cost: Duration::ZERO,
+            refresh_at: refresh_after.map(|d| now + d),
+            refresh_after,
+            refreshing: false,
+            version: 0,
+            stamp: 0,
+            seq: 0,
+        }
+    }
+
+    /// Records an in-place change as `version`, so that a reload started before it is
+    /// discarded instead of undoing it. The next stale read starts a new reload.
+    fn changed(&mut self, version: u64) {
+        self.version = version;
+        self.refreshing = false;
+    }
+
+    fn is_live(&self, now: Instant) -> bool {
+        self.expire_at.is_none_or(|t| now < t)
+    }
+
+    fn is_fresh(&self, now: Instant) -> bool {
+        self.refresh_at.is_none_or(|t| now < t)
+    }
+
+    /// Decides whether to treat the entry as expired ahead of its deadline (XFetch).
+    ///
+    /// The entry expires early when `now + cost * beta * -ln(u)` reaches the deadline,
+    /// with `u` uniform in `(0, 1]`. The chance rises as the deadline approaches and is
+    /// larger for values that are expensive to recompute.
+    fn expires_early(&self, now: Instant, beta: f64, rng: &Rng) -> bool {
+        let Some(expire_at) = self.expire_at else {
+            return false;
+        };
+        if self.cost.is_zero() {
+            return false;
+        }
+        let u = 1.0 - rng.next_f64();
+        let gap = self.cost.as_secs_f64() * beta * -u.ln();
+        Duration::try_from_secs_f64(gap).map_or(true, |gap| now + gap >= expire_at)
+    }
+}
+
+/// A live entry found by [`MiniCache::lookup`].
+struct Hit<V> {
+    value: V,
+    fresh: bool,
+    /// Version of the entry if the caller should start a background reload.
+    reload: Option<u64>,
+}
+
+/// Entries evicted to make room, with their keys.
+type Evicted<K, V> = Vec<(K, Entry<V>)>;
+
+/// What a write replaced in the cache: the previous entry, if there was one, and its
+/// tags. Kept so that a write the store rejects can be undone.
+struct Replaced<V> {
+    entry: Option<Entry<V>>,
+    tags: Vec<String>,
+}
+
+/// The entry map plus the recency queue used to evict entries once a capacity is set.
+///
+/// Every insert or read of a capacity-bounded cache pushes `(key, stamp)` onto `order`.
+/// Queue items whose stamp no longer matches the entry are stale and are skipped on
+/// eviction, so the front of the queue always leads to the least recently used entry.
+///
+/// `tags` maps each tag to the keys carrying it and `tagged` maps each key to its tags.
+/// A key stays tagged while it lives in memory or in the disk tier.
+///
+/// `seqs` orders the keys by the sequence number they were first inserted with, which
+/// gives [`MiniCache::scan`] cursors that stay valid while the map grows or shrinks.
+/// `index`, if the cache is ordered, holds the same keys as `map` in sorted order.
+struct Storage<K, V> {
+    map: HashMap<K, Entry<V>>,
+    order: VecDeque<(K, u64)>,
+    tick: u64,
+    versions: u64,
+    capacity: Option<usize>,
+    tags: HashMap<String, HashSet<K>>,
+    tagged: HashMap<K, Vec<String>>,
+    seqs: BTreeMap<u64, K>,
+    next_seq: u64,
+    index: Option<Box<dyn KeyIndex<K>>>,
+    waiters: HashMap<K, Arc<Notify>>,
+}
+
+impl<K, V> Storage<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new(capacity: Option<usize>, index: Option<Box<dyn KeyIndex<K>>>) -> Self {
+        Storage {
+            map: HashMap::new(),
+            order: VecDeque::new(),
+            tick: 0,
+            versions: 0,
+            capacity,
+            tags: HashMap::new(),
+            tagged: HashMap::new(),
+            seqs: BTreeMap::new(),
+            next_seq: 0,
+            index,
+            waiters: HashMap::new(),
+        }
+    }
+
+    /// Inserts an entry and returns whatever had to be evicted to stay within capacity.
+    fn insert(&mut self, key: K, entry: Entry<V>) -> Vec<(K, Entry<V>)> {
+        self.insert_replacing(key, entry).1
+    }
+
+    /// Inserts an entry like [`insert`](Self::insert), also returning the entry it
+    /// replaced.
+    fn insert_replacing(
+        &mut self,
+        key: K,
+        mut entry: Entry<V>,
+    ) -> (Option<Entry<V>>, Evicted<K, V>) {
+        entry.stamp = self.next_stamp(&key);
+        self.versions += 1;
+        entry.version = self.versions;
+        entry.seq = match self.map.get(&key) {
+            Some(old) => old.seq,
+            None => {
+                self.next_seq += 1;
+                self.seqs.insert(self.next_seq, key.clone());
+                if let Some(index) = &mut self.index {
+                    index.insert(key.clone());
+                }
+                self.next_seq
+            }
+        };
+        if let Some(waiters) = self.waiters.remove(&key) {
+            waiters.notify_waiters();
+        }
+        let previous = self.map.insert(key, entry);
+
+        let mut evicted = Vec::new();
+        if let Some(capacity) = self.capacity {
+            while self.map.len() > capacity {
+                match self.pop_lru() {
+                    Some(entry) => evicted.push(entry),
+                    None => break,
+                }
+            }
+            self.compact();
+        }
+        (previous, evicted)
+    }
+
+    /// Takes the next write version.
+    fn next_version(&mut self) -> u64 {
+        self.versions += 1;
+        self.versions
+    }
+
+    /// Marks `key` as most recently used.
+    fn touch(&mut self, key: &K) {
+        if self.capacity.is_none() {
+            return;
+        }
+        let stamp = self.next_stamp(key);
+        if let Some(entry) = self.map.get_mut(key) {
+            entry.stamp = stamp;
+        }
+        self.compact();
+    }
+
+    fn next_stamp(&mut self, key: &K) -> u64 {
+        if self.capacity.is_none() {
+            return 0;
+        }
+        self.tick += 1;
+        self.order.push_back((key.clone(), self.tick));
+        self.tick
+    }
+
+    fn pop_lru(&mut self) -> Option<(K, Entry<V>)> {
+        while let Some((key, stamp)) = self.order.pop_front() {
+            if self.map.get(&key).is_some_and(|e| e.stamp == stamp) {
+                let (key, entry) = self.map.remove_entry(&key)?;
+                self.seqs.remove(&entry.seq);
+                if let Some(index) = &mut self.index {
+                    index.remove(&key);
+                }
+                return Some((key, entry));
+            }
+        }
+        None
+    }
+
+    /// Drops stale queue items once they outnumber the live entries.
+    fn compact(&mut self) {
+        if self.order.len() > 2 * self.map.len() + 32 {
+            let map = &self.map;
+            self.order
+                .retain(|(k, stamp)| map.get(k).is_some_and(|e| e.stamp == *stamp));
+        }
+    }
+
+    /// Removes `key` and its tags.
+    fn remove(&mut self, key: &K) -> Option<Entry<V>> {
+        self.untag(key);
+        let entry = self.map.remove(key)?;
+        self.seqs.remove(&entry.seq);
+        if let Some(index) = &mut self.index {
+            index.remove(key);
+        }
+        Some(entry)
+    }
+
+    /// Removes every expired entry together with its tags.
+    fn purge_expired(&mut self, now: Instant) {
+        let expired: Vec<K> = self
+            .map
+            .iter()
+            .filter(|(_, entry)| !entry.is_live(now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            self.remove(key);
+        }
+        self.compact();
+    }
+
+    /// Replaces the tags of `key`.
+    fn set_tags(&mut self, key: &K, tags: Vec<String>) {
+        self.untag(key);
+        if tags.is_empty() {
+            return;
+        }
+        for tag in &tags {
+            self.tags
+                .entry(tag.clone())
+                .or_default()
+                .insert(key.clone());
+        }
+        self.tagged.insert(key.clone(), tags);
+    }
+
+    fn untag(&mut self, key: &K) {
+        let Some(tags) = self.tagged.remove(key) else {
+            return;
+        };
+        for tag in tags {
+            if let Some(keys) = self.tags.get_mut(&tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.tags.remove(&tag);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+        self.tags.clear();
+        self.tagged.clear();
+        self.seqs.clear();
+        if let Some(index) = &mut self.index {
+            index.clear();
+        }
+    }
+}
+
+impl<K, V> Storage<K, V>
+where
+    K: Ord + Hash + Eq + Clone,
+{
+    /// Walks the keys within the bounds in ascending order, through the sorted index if
+    /// there is one and by sorting the matching keys otherwise.
+    fn sorted_keys<'a>(
+        &'a self,
+        lower: Bound<&'a K>,
+        upper: Bound<&'a K>,
+    ) -> Box<dyn DoubleEndedIterator<Item = &'a K> + 'a> {
+        if is_inverted(lower, upper) {
+            return Box::new(std::iter::empty());
+        }
+        match &self.index {
+            Some(index) => index.range(lower, upper),
+            None => {
+                let mut keys: Vec<&K> = self
+                    .map
+                    .keys()
+                    .filter(|k| (lower, upper).contains(*k))
+                    .collect();
+                keys.sort();
+                Box::new(keys.into_iter())
+            }
+        }
+    }
+}
+
+/// Returns `true` if no key can lie within the bounds, which `BTreeSet::range` would
+/// reject with a panic.
+fn is_inverted<K: Ord>(lower: Bound<&K>, upper: Bound<&K>) -> bool {
+    match (lower, upper) {
+        (Bound::Included(lo), Bound::Included(hi)) => lo > hi,
+        (Bound::Included(lo) | Bound::Excluded(lo), Bound::Included(hi) | Bound::Excluded(hi)) => {
+            lo >= hi
+        }
+        _ => false,
+    }
+}
 
 /// A fast, thread-safe, async-compatible in-memory cache with TTL support and automatic cleanup.
 ///
@@ -69,11 +491,25 @@ type CacheMap<K, V> = Arc<RwLock<HashMap<K, (V, Option<Instant>)>>>;
 #[derive(Clone)]
 pub struct MiniCache<K, V> {
     inner: CacheMap<K, V>,
+    tier: Option<Tier<K, V>>,
+    backing: Option<Arc<Backing<K, V>>>,
+    refresh: Option<Arc<Refresh<K, V>>>,
+    early_expiration: Option<f64>,
+    jitter: Option<Jitter>,
+    rng: Arc<Rng>,
+    seed: Option<u64>,
+    clock: Arc<dyn Clock>,
+    index: Option<IndexFactory<K>>,
+    cleanup_interval: Duration,
+    namespaces: Arc<Mutex<HashMap<String, Namespace<K, V>>>>,
+    broker: Arc<Broker<V>>,
+    /// Keys a `get_with` loader is running for.
+    loads: Arc<KeyLocks<K>>,
 }
 
 impl<K, V> MiniCache<K, V>
 where
-    K: std::hash::Hash + Eq + Clone + Send + Sync + 'static,
+    K: Hash + Eq + Clone + Send + Sync + 'static,
     V: Clone + Send + Sync + 'static,
 {
     /// Creates a new `MiniCache` with the specified cleanup interval.
@@ -103,34 +539,271 @@ where
     /// }
     /// ```
     pub fn new(cleanup_interval: Duration) -> Self {
+        Self::builder(cleanup_interval).build()
+    }
+
+    /// Returns a [`MiniCacheBuilder`] for configuring optional features such as a
+    /// capacity limit or a disk tier.
+    ///
+    /// # Arguments
+    ///
+    /// * `cleanup_interval` - How often to run the background cleanup task
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::builder(Duration::from_secs(60))
+    ///         .capacity(2)
+    ///         .build();
+    ///
+    ///     cache.set("key1", "value1", None).await;
+    ///     cache.set("key2", "value2", None).await;
+    ///     cache.set("key3", "value3", None).await;
+    ///
+    ///     // The least recently used entry was evicted
+    ///     assert_eq!(cache.get(&"key1").await, None);
+    ///     assert_eq!(cache.len().await, 2);
+    /// }
+    /// ```
+    pub fn builder(cleanup_interval: Duration) -> MiniCacheBuilder<K, V> {
+        MiniCacheBuilder::new(cleanup_interval)
+    }
+
+    /// Creates the cache described by a builder and starts its cleanup task.
+    pub(crate) fn from_builder(builder: MiniCacheBuilder<K, V>) -> Self {
         let cache = MiniCache {
-            inner: Arc::new(RwLock::new(HashMap::new())),
+            inner: Arc::new(RwLock::new(Storage::new(
+                builder.capacity,
+                builder.index.map(|factory| factory()),
+            ))),
+            tier: builder.tier,
+            backing: builder.backing.map(Arc::new),
+            refresh: builder.refresh.map(Arc::new),
+            early_expiration: builder.early_expiration,
+            jitter: builder.jitter,
+            rng: Arc::new(builder.seed.map_or_else(Rng::from_entropy, Rng::with_seed)),
+            seed: builder.seed,
+            clock: builder.clock,
+            index: builder.index,
+            cleanup_interval: builder.cleanup_interval,
+            namespaces: Arc::default(),
+            broker: Arc::default(),
+            loads: Arc::default(),
         };
-        cache.spawn_cleaner(cleanup_interval);
+        cache.spawn_cleaner(builder.cleanup_interval);
+        if let Some(backing) = &cache.backing {
+            backing.spawn_flusher();
+        }
         cache
     }
 
     /// Spawns a background task that periodically removes expired entries.
     ///
     /// This method is called automatically by `new()` and doesn't need to be
-    /// called manually.
+    /// called manually. The task stops once every handle to the cache is dropped.
     fn spawn_cleaner(&self, interval_duration: Duration) {
-        let map = self.inner.clone();
+        let map = Arc::downgrade(&self.inner);
+        let tier = self.tier.clone();
+        let clock = self.clock.clone();
         tokio::spawn(async move {
             let mut ticker = interval(interval_duration);
             loop {
                 ticker.tick().await;
-                let now = Instant::now();
+                // Stop once every handle to the cache is gone
+                let Some(map) = map.upgrade() else {
+                    return;
+                };
+                let now = clock.now();
                 let mut write_guard = map.write().await;
-                write_guard.retain(|_, (_, expire_at)| expire_at.map_or(true, |t| now < t));
+                write_guard.purge_expired(now);
+                if let Some(tier) = &tier {
+                    tier.purge_expired(now);
+                    // Forget the tags of entries that expired on disk
+                    if !write_guard.tagged.is_empty() {
+                        let on_disk: HashSet<K> = tier.keys(now).into_iter().collect();
+                        let gone: Vec<K> = write_guard
+                            .tagged
+                            .keys()
+                            .filter(|k| !write_guard.map.contains_key(k) && !on_disk.contains(k))
+                            .cloned()
+                            .collect();
+                        for key in &gone {
+                            write_guard.untag(key);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Builds an entry written at `now`, scheduling its refresh if refresh-ahead is on.
+    fn new_entry(
+        &self,
+        value: V,
+        expire_at: Option<Instant>,
+        ttl: Option<Duration>,
+        now: Instant,
+    ) -> Entry<V> {
+        let refresh_after = self.refresh.as_ref().map(|r| r.after);
+        Entry::new(value, expire_at, ttl, refresh_after, now)
+    }
+
+    /// Applies the configured TTL jitter, never going below zero.
+    fn jittered(&self, ttl: Duration) -> Duration {
+        let spread = match self.jitter {
+            Some(Jitter::Percent(percent)) => ttl.as_secs_f64() * percent / 100.0,
+            Some(Jitter::Absolute(range)) => range.as_secs_f64(),
+            None => return ttl,
+        };
+        let secs = ttl.as_secs_f64() + spread * self.rng.next_signed_f64();
+        Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(ttl)
+    }
+
+    /// Hands evicted entries that are still live to the disk tier, if there is one,
+    /// and forgets the tags of entries that are dropped.
+    fn spill(&self, storage: &mut Storage<K, V>, evicted: Vec<(K, Entry<V>)>, now: Instant) {
+        for (key, entry) in evicted {
+            match &self.tier {
+                Some(tier) if entry.is_live(now) => {
+                    tier.spill(key, entry.value, entry.expire_at, entry.ttl)
+                }
+                _ => storage.untag(&key),
+            }
+        }
+    }
+
+    /// Reloads `key` in the background through `loader`.
+    ///
+    /// The new value replaces the entry with its original TTL and refresh interval. If
+    /// the loader fails, the old value is kept until it expires and the next read tries
+    /// again. If the entry was overwritten or its TTL changed while the loader ran, the
+    /// reloaded value is discarded.
+    fn spawn_reload(&self, key: K, version: u64, loader: Loader<K, V>) {
+        let load = loader(key.clone());
+        let cache = self.clone();
+        tokio::spawn(async move {
+            let started = cache.clock.now();
+            let result = load.await;
+            let now = cache.clock.now();
+            let mut storage = cache.inner.write().await;
+            let Some(entry) = storage.map.get_mut(&key) else {
+                return;
+            };
+            if entry.version != version {
+                return;
+            }
+            match result {
+                Ok(value) => {
+                    let (ttl, refresh_after) = (entry.ttl, entry.refresh_after);
+                    let mut entry =
+                        Entry::new(value, ttl.map(|d| now + d), ttl, refresh_after, now);
+                    entry.cost = now - started;
+                    let evicted = storage.insert(key, entry);
+                    cache.spill(&mut storage, evicted, now);
+                }
+                Err(_) => entry.refreshing = false,
             }
         });
     }
 
+    /// Looks `key` up in memory, promoting it from the disk tier if needed.
+    ///
+    /// When `can_reload` is set and the entry is stale with no reload in flight, the
+    /// entry is marked as reloading and the hit carries its version. With early
+    /// expiration enabled, an entry may be reported as missing shortly before its
+    /// deadline; it is left in place for other readers.
+    async fn lookup(&self, key: &K, can_reload: bool) -> Option<Hit<V>> {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let entry = self.live_entry(&mut storage, key, now)?;
+        if let Some(beta) = self.early_expiration
+            && entry.expires_early(now, beta, &self.rng)
+        {
+            return None;
+        }
+        let fresh = entry.is_fresh(now);
+        let reload = (!fresh && can_reload && !entry.refreshing).then(|| {
+            entry.refreshing = true;
+            entry.version
+        });
+        let value = entry.value.clone();
+        storage.touch(key);
+        Some(Hit {
+            value,
+            fresh,
+            reload,
+        })
+    }
+
+    /// Takes the write lock with `key` promoted from the disk tier, if it is there.
+    ///
+    /// The spilled entry is read without holding the lock, then claimed under it. If
+    /// the key was written or spilled again meanwhile, the read is retried.
+    async fn lock_key(&self, key: &K) -> RwLockWriteGuard<'_, Storage<K, V>> {
+        let mut storage = self.inner.write().await;
+        let Some(tier) = &self.tier else {
+            return storage;
+        };
+        for _ in 0..PROMOTE_ATTEMPTS {
+            let now = self.clock.now();
+            if storage.map.contains_key(key) || !tier.contains(key, now) {
+                return storage;
+            }
+            drop(storage);
+            let spilled = {
+                let (tier, key) = (tier.clone(), key.clone());
+                tokio::task::spawn_blocking(move || tier.peek(&key, now))
+                    .await
+                    .ok()
+                    .flatten()
+            };
+            storage = self.inner.write().await;
+            let Some(spilled) = spilled else {
+                continue;
+            };
+            if !storage.map.contains_key(key) && tier.claim(key, spilled.token) {
+                let entry = self.new_entry(spilled.value, spilled.expire_at, spilled.ttl, now);
+                let evicted = storage.insert(key.clone(), entry);
+                self.spill(&mut storage, evicted, now);
+                return storage;
+            }
+        }
+        storage
+    }
+
+    /// Returns the live in-memory entry for `key`, dropping it if it has expired.
+    ///
+    /// Callers take the lock with [`lock_key`](Self::lock_key) so that an entry on the
+    /// disk tier has already been promoted.
+    fn live_entry<'a>(
+        &self,
+        storage: &'a mut Storage<K, V>,
+        key: &K,
+        now: Instant,
+    ) -> Option<&'a mut Entry<V>> {
+        if storage.map.get(key).is_some_and(|e| !e.is_live(now)) {
+            storage.remove(key);
+            return None;
+        }
+        storage.map.get_mut(key)
+    }
+
     /// Stores a key-value pair in the cache with an optional TTL.
     ///
     /// If a TTL is specified, the entry will automatically expire after that duration.
     /// If the key already exists, it will be overwritten with the new value and TTL.
+    /// When [`MiniCacheBuilder::ttl_jitter`] is configured, the TTL is randomly spread
+    /// before it is applied.
+    ///
+    /// With a write-through store the value is cached and then written to the store;
+    /// if that write fails, the previous entry is put back, with its TTL and tags,
+    /// unless another write has replaced the new one meanwhile. Use [`try_set`](Self::try_set) to see the
+    /// error. With a write-behind store the write is queued and flushed later.
     ///
     /// # Arguments
     ///
@@ -156,88 +829,92 @@ where
     /// }
     /// ```
     pub async fn set(&self, key: K, value: V, ttl: Option<Duration>) {
-        let expire_at = ttl.map(|d| Instant::now() + d);
-        self.inner.write().await.insert(key, (value, expire_at));
+        let _ = self
+            .write(key, value, ttl, Vec::new(), Duration::ZERO)
+            .await;
     }
 
-    /// Retrieves a value from the cache by key.
-    ///
-    /// If the key exists and hasn't expired, returns `Some(value)`.
-    /// If the key doesn't exist or has expired, returns `None`.
-    /// Expired entries are automatically removed when accessed.
-    ///
-    /// # Arguments
-    ///
-    /// * `key` - The key to look up
+    /// Stores a key-value pair like [`set`](Self::set), returning the error if a
+    /// write-through store rejects the write.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// `Some(value)` if the key exists and is not expired, `None` otherwise
+    /// Returns the store's error; the key is then left as it was before the call,
+    /// unless another write replaced it in the meantime.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use minicache::MiniCache;
+    /// use minicache::{MemoryStore, MiniCache};
     /// use std::time::Duration;
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let cache = MiniCache::new(Duration::from_secs(60));
-    ///     
-    ///     cache.set("key1", "value1", None).await;
-    ///     
-    ///     match cache.get(&"key1").await {
-    ///         Some(value) => println!("Found: {}", value),
-    ///         None => println!("Key not found or expired"),
-    ///     }
+    ///     let store = MemoryStore::new();
+    ///     let cache = MiniCache::builder(Duration::from_secs(60))
+    ///         .write_through(store.clone())
+    ///         .build();
+    ///
+    ///     cache.try_set("key1", "value1", None).await.unwrap();
+    ///     assert_eq!(store.get(&"key1"), Some("value1"));
     /// }
     /// ```
-    pub async fn get(&self, key: &K) -> Option<V> {
-        let mut map = self.inner.write().await;
-        if let Some((v, expire_at)) = map.get(key) {
-            if expire_at.map_or(true, |t| Instant::now() < t) {
-                return Some(v.clone());
-            } else {
-                map.remove(key);
-            }
-        }
-        None
+    pub async fn try_set(&self, key: K, value: V, ttl: Option<Duration>) -> Result<(), StoreError> {
+        self.write(key, value, ttl, Vec::new(), Duration::ZERO)
+            .await
     }
 
-    /// Removes a key from the cache manually.
+    /// Stores a key-value pair like [`set`](Self::set), recording how long the value
+    /// took to compute.
     ///
-    /// This immediately removes the key-value pair from the cache, regardless
-    /// of any TTL that may have been set.
+    /// With [`MiniCacheBuilder::early_expiration`] enabled, the cost makes the entry
+    /// eligible for early expiration: the more expensive it is to recompute, the
+    /// earlier a `get` may start reporting it as missing. Entries written with `set`
+    /// have no cost and only expire at their deadline.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key to remove
+    /// * `key` - The key to store
+    /// * `value` - The value to associate with the key
+    /// * `ttl` - Optional time-to-live duration. If `None`, the entry never expires
+    /// * `cost` - How long computing `value` took
     ///
     /// # Examples
     ///
     /// ```rust
     /// use minicache::MiniCache;
-    /// use std::time::Duration;
+    /// use std::time::{Duration, Instant};
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let cache = MiniCache::new(Duration::from_secs(60));
-    ///     
-    ///     cache.set("key1", "value1", None).await;
-    ///     assert_eq!(cache.get(&"key1").await, Some("value1"));
-    ///     
-    ///     cache.remove(&"key1").await;
-    ///     assert_eq!(cache.get(&"key1").await, None);
+    ///     let cache = MiniCache::builder(Duration::from_secs(60))
+    ///         .early_expiration(1.0)
+    ///         .build();
+    ///
+    ///     let started = Instant::now();
+    ///     let report = "expensive report".to_string();
+    ///     cache
+    ///         .set_with_cost("report", report, Some(Duration::from_secs(300)), started.elapsed())
+    ///         .await;
     /// }
     /// ```
-    pub async fn remove(&self, key: &K) {
-        self.inner.write().await.remove(key);
+    pub async fn set_with_cost(&self, key: K, value: V, ttl: Option<Duration>, cost: Duration) {
+        let _ = self.write(key, value, ttl, Vec::new(), cost).await;
     }
 
-    /// Removes all entries from the cache.
+    /// Stores a key-value pair like [`set`](Self::set) and attaches `tags` to it.
     ///
-    /// This operation clears the entire cache, removing all key-value pairs
-    /// regardless of their expiration status.
+    /// Every entry carrying a tag can later be removed at once with
+    /// [`invalidate_tag`](Self::invalidate_tag). Tags replace any tags the key had
+    /// before, and are forgotten when the entry is overwritten by `set`, removed,
+    /// expires or is evicted without a disk tier to spill to.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to store
+    /// * `value` - The value to associate with the key
+    /// * `ttl` - Optional time-to-live duration. If `None`, the entry never expires
+    /// * `tags` - The tags to attach to the entry
     ///
     /// # Examples
     ///
@@ -248,31 +925,148 @@ where
     /// #[tokio::main]
     /// async fn main() {
     ///     let cache = MiniCache::new(Duration::from_secs(60));
-    ///     
-    ///     cache.set("key1", "value1", None).await;
-    ///     cache.set("key2", "value2", None).await;
-    ///     assert_eq!(cache.len().await, 2);
-    ///     
-    ///     cache.clear().await;
-    ///     assert_eq!(cache.len().await, 0);
+    ///
+    ///     cache.set_tagged("/products", "listing", None, ["product:42"]).await;
+    ///     cache.set_tagged("/search?q=lamp", "results", None, ["product:42", "search"]).await;
+    ///     cache.set("/about", "about", None).await;
+    ///
+    ///     assert_eq!(cache.invalidate_tag("product:42").await, 2);
+    ///     assert_eq!(cache.keys().await, vec!["/about"]);
     /// }
     /// ```
-    pub async fn clear(&self) {
-        self.inner.write().await.clear();
+    pub async fn set_tagged<I>(&self, key: K, value: V, ttl: Option<Duration>, tags: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let tags = tags.into_iter().map(Into::into).collect();
+        let _ = self.write(key, value, ttl, tags, Duration::ZERO).await;
     }
 
-    /// Checks if a key exists in the cache and has not expired.
-    ///
-    /// This is equivalent to calling `get(key).is_some()` but doesn't return
-    /// the actual value, which can be more efficient for existence checks.
-    ///
-    /// # Arguments
+    /// Caches the entry with `tags`, then writes it through to the store.
     ///
-    /// * `key` - The key to check
+    /// The key's store guard is held across both steps, so the store receives writes
+    /// to a key in the same order as the cache.
+    async fn write(
+        &self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+        tags: Vec<String>,
+        cost: Duration,
+    ) -> Result<(), StoreError> {
+        let Some(backing) = &self.backing else {
+            self.cache_entry(key, value, ttl, tags, cost).await;
+            return Ok(());
+        };
+        let _guard = backing.guard(&key).await;
+        let (version, replaced) = self
+            .cache_entry(key.clone(), value.clone(), ttl, tags, cost)
+            .await;
+        self.store_through(&key, &value, version, replaced).await
+    }
+
+    /// Caches `value` for `key` with a jittered TTL, `tags` and its recompute `cost`,
+    /// and returns the new entry's version together with what it replaced.
+    async fn cache_entry(
+        &self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+        tags: Vec<String>,
+        cost: Duration,
+    ) -> (u64, Replaced<V>) {
+        let now = self.clock.now();
+        let ttl = ttl.map(|d| self.jittered(d));
+        let mut entry = self.new_entry(value, ttl.map(|d| now + d), ttl, now);
+        entry.cost = cost;
+        self.store_entry(key, entry, tags).await
+    }
+
+    /// Caches `entry` with `tags`, replacing whatever was cached for `key` in memory or
+    /// on disk along with its tags, and returns the entry's version together with what
+    /// it replaced.
     ///
-    /// # Returns
+    /// With a backing store, a spilled entry is promoted first, so that it can be put
+    /// back if the store rejects the write.
+    async fn store_entry(&self, key: K, entry: Entry<V>, tags: Vec<String>) -> (u64, Replaced<V>) {
+        let mut storage = match self.backing {
+            Some(_) => self.lock_key(&key).await,
+            None => self.inner.write().await,
+        };
+        let now = self.clock.now();
+        if let Some(tier) = &self.tier {
+            tier.remove(&key);
+        }
+        self.insert_replacing(&mut storage, key, entry, tags, now)
+    }
+
+    /// Inserts `entry`, spills what it evicts and returns the entry's version.
+    fn insert(&self, storage: &mut Storage<K, V>, key: K, entry: Entry<V>, now: Instant) -> u64 {
+        let evicted = storage.insert(key, entry);
+        self.spill(storage, evicted, now);
+        storage.versions
+    }
+
+    /// Inserts `entry` with `tags` like [`insert`](Self::insert), and also returns what
+    /// it replaced.
+    fn insert_replacing(
+        &self,
+        storage: &mut Storage<K, V>,
+        key: K,
+        entry: Entry<V>,
+        tags: Vec<String>,
+        now: Instant,
+    ) -> (u64, Replaced<V>) {
+        let previous_tags = storage.tagged.get(&key).cloned().unwrap_or_default();
+        storage.set_tags(&key, tags);
+        let (previous, evicted) = storage.insert_replacing(key, entry);
+        self.spill(storage, evicted, now);
+        let replaced = Replaced {
+            entry: previous,
+            tags: previous_tags,
+        };
+        (storage.versions, replaced)
+    }
+
+    /// Puts back what a write to `key` replaced, or drops the key if it was absent or
+    /// has expired since.
+    fn restore(&self, storage: &mut Storage<K, V>, key: &K, replaced: Replaced<V>) {
+        let now = self.clock.now();
+        match replaced.entry {
+            Some(mut entry) if entry.is_live(now) => {
+                // A reload of the old entry was abandoned when the write replaced it
+                entry.refreshing = false;
+                storage.set_tags(key, replaced.tags);
+                self.insert(storage, key.clone(), entry, now);
+            }
+            _ => {
+                storage.remove(key);
+            }
+        }
+    }
+
+    /// Retrieves a value from the cache by key.
     ///
-    /// `true` if the key exists and is not expired, `false` otherwise
+    /// If the key exists and hasn't expired, returns `Some(value)`.
+    /// If the key doesn't exist or has expired, returns `None`.
+    /// Expired entries are automatically removed when accessed.
+    ///
+    /// If a backing store is configured, a miss loads the value from the store and
+    /// caches it without a TTL. A failed load counts as a miss; use
+    /// [`try_get`](Self::try_get) to see the error.
+    ///
+    /// With refresh-ahead configured, a hit on an entry that is past its refresh point
+    /// still returns the current value immediately and starts a single background
+    /// reload through the registered loader.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to look up
+    ///
+    /// # Returns
+    ///
+    /// `Some(value)` if the key exists and is not expired, `None` otherwise
     ///
     /// # Examples
     ///
@@ -286,24 +1080,403 @@ where
     ///     
     ///     cache.set("key1", "value1", None).await;
     ///     
-    ///     if cache.contains(&"key1").await {
-    ///         println!("Key exists!");
+    ///     match cache.get(&"key1").await {
+    ///         Some(value) => println!("Found: {}", value),
+    ///         None => println!("Key not found or expired"),
     ///     }
     /// }
     /// ```
-    pub async fn contains(&self, key: &K) -> bool {
-        self.get(key).await.is_some()
+    pub async fn get(&self, key: &K) -> Option<V> {
+        self.try_get(key).await.ok().flatten()
     }
 
-    /// Returns the number of valid (non-expired) entries in the cache.
+    /// Retrieves a value like [`get`](Self::get), returning the error if loading a
+    /// miss from the backing store fails.
     ///
-    /// This method iterates through all entries and counts only those that
-    /// haven't expired. Note that this operation requires checking expiration
-    /// times, so it has O(n) complexity.
+    /// # Errors
+    ///
+    /// Returns the store's error; nothing is cached for the key in that case.
+    pub async fn try_get(&self, key: &K) -> Result<Option<V>, StoreError> {
+        if let Some(value) = self.get_cached(key).await {
+            return Ok(Some(value));
+        }
+        let Some(backing) = &self.backing else {
+            return Ok(None);
+        };
+        // A write or remove of the key cannot land between the load and the insert
+        let _guard = backing.guard(key).await;
+        let Some(value) = backing.load(key).await? else {
+            return Ok(None);
+        };
+        Ok(Some(self.insert_if_absent(key.clone(), value, None).await))
+    }
+
+    /// Looks `key` up in memory and in the disk tier, without consulting the store.
+    async fn get_cached(&self, key: &K) -> Option<V> {
+        let hit = self.lookup(key, self.refresh.is_some()).await?;
+        if let (Some(version), Some(refresh)) = (hit.reload, &self.refresh) {
+            self.spawn_reload(key.clone(), version, refresh.loader.clone());
+        }
+        Some(hit.value)
+    }
+
+    /// Returns the value for `key`, waiting up to `timeout` for another task to set it.
+    ///
+    /// Resolves as soon as the key is written by any operation, such as `set`,
+    /// `set_tagged` or `incr`. Each key has its own notifier, so a write only wakes the
+    /// tasks waiting for that key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to wait for
+    /// * `timeout` - How long to wait if the key is missing
     ///
     /// # Returns
     ///
-    /// The number of valid entries currently in the cache
+    /// `Some(value)` if the key exists or is set before the timeout, `None` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     let worker = cache.clone();
+    ///     tokio::spawn(async move {
+    ///         worker.set("job:1", "done", None).await;
+    ///     });
+    ///
+    ///     let result = cache.wait_for(&"job:1", Duration::from_secs(5)).await;
+    ///     assert_eq!(result, Some("done"));
+    ///
+    ///     let missing = cache.wait_for(&"job:2", Duration::from_millis(10)).await;
+    ///     assert_eq!(missing, None);
+    /// }
+    /// ```
+    pub async fn wait_for(&self, key: &K, timeout: Duration) -> Option<V> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(value) = self.get(key).await {
+                return Some(value);
+            }
+            let mut storage = self.lock_key(key).await;
+            let now = self.clock.now();
+            // Checked again under the lock, so a write cannot slip in before we listen
+            if let Some(entry) = self.live_entry(&mut storage, key, now) {
+                return Some(entry.value.clone());
+            }
+            let notify = storage.waiters.entry(key.clone()).or_default().clone();
+            let notified = notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            drop(storage);
+
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                let mut storage = self.inner.write().await;
+                // Only the map and this call hold the notifier when no one else waits
+                if Arc::strong_count(&notify) == 2
+                    && storage
+                        .waiters
+                        .get(key)
+                        .is_some_and(|current| Arc::ptr_eq(current, &notify))
+                {
+                    storage.waiters.remove(key);
+                }
+                return None;
+            }
+        }
+    }
+
+    /// Returns the value for `key`, computing it with `loader` on a miss and serving
+    /// stale values while they are revalidated.
+    ///
+    /// Entries written by this method are fresh for `policy`'s fresh TTL and are then
+    /// kept for its stale window:
+    ///
+    /// * a fresh entry is returned as [`Lookup::Fresh`];
+    /// * a stale entry is returned as [`Lookup::Stale`] and one background call to
+    ///   `loader` revalidates it. If that call fails, the stale value keeps being served
+    ///   until the stale window ends, and the next call tries again;
+    /// * a miss awaits `loader`, caches the result and returns [`Lookup::Loaded`].
+    ///   Concurrent misses of one key share a single call: the other callers wait for
+    ///   it and get the cached value, or try `loader` themselves if it failed.
+    ///
+    /// The loaded entry drops any tags the key had, and
+    /// [`ttl_jitter`](MiniCacheBuilder::ttl_jitter) spreads its total lifetime.
+    ///
+    /// # Errors
+    ///
+    /// Returns the loader's error if the key was missing and `loader` failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{LoaderError, Lookup, MiniCache, StalePolicy};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), LoaderError> {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     let policy = StalePolicy::new(Duration::from_secs(30), Duration::from_secs(300));
+    ///
+    ///     let fetch = |id: u32| async move { Ok::<_, LoaderError>(format!("user-{id}")) };
+    ///
+    ///     let first = cache.get_with(7, policy, fetch).await?;
+    ///     assert_eq!(first, Lookup::Loaded("user-7".to_string()));
+    ///
+    ///     let second = cache.get_with(7, policy, fetch).await?;
+    ///     assert_eq!(second, Lookup::Fresh("user-7".to_string()));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_with<F, Fut, E>(
+        &self,
+        key: K,
+        policy: StalePolicy,
+        loader: F,
+    ) -> Result<Lookup<V>, LoaderError>
+    where
+        F: Fn(K) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<V, E>> + Send + 'static,
+        E: Into<LoaderError>,
+    {
+        let loader = loader::boxed(loader);
+        if let Some(found) = self.lookup_with(&key, &loader).await {
+            return Ok(found);
+        }
+        // Concurrent misses of a key wait for the first caller's load instead of
+        // running their own
+        let _loading = self.loads.lock(&key).await;
+        if let Some(found) = self.lookup_with(&key, &loader).await {
+            return Ok(found);
+        }
+
+        let started = self.clock.now();
+        let value = loader(key.clone()).await?;
+        let now = self.clock.now();
+        let ttl = self.jittered(policy.fresh + policy.stale);
+        let mut entry = Entry::new(
+            value.clone(),
+            Some(now + ttl),
+            Some(ttl),
+            Some(policy.fresh),
+            now,
+        );
+        entry.cost = now - started;
+        self.store_entry(key, entry, Vec::new()).await;
+        Ok(Lookup::Loaded(value))
+    }
+
+    /// Serves a hit for [`get_with`](Self::get_with), starting a background reload
+    /// through `loader` if the entry is stale.
+    async fn lookup_with(&self, key: &K, loader: &Loader<K, V>) -> Option<Lookup<V>> {
+        let hit = self.lookup(key, true).await?;
+        if hit.fresh {
+            return Some(Lookup::Fresh(hit.value));
+        }
+        if let Some(version) = hit.reload {
+            self.spawn_reload(key.clone(), version, loader.clone());
+        }
+        Some(Lookup::Stale(hit.value))
+    }
+
+    /// Caches a loaded value unless a live entry was written meanwhile, and returns
+    /// whichever value ends up cached.
+    pub(crate) async fn insert_if_absent(&self, key: K, value: V, ttl: Option<Duration>) -> V {
+        let mut storage = self.lock_key(&key).await;
+        let now = self.clock.now();
+        if let Some(entry) = self.live_entry(&mut storage, &key, now) {
+            return entry.value.clone();
+        }
+        let entry = self.new_entry(value.clone(), ttl.map(|d| now + d), ttl, now);
+        storage.untag(&key);
+        self.insert(&mut storage, key, entry, now);
+        value
+    }
+
+    /// Replaces the value of `key` with `f(current)` while holding the write lock, and
+    /// returns the new value.
+    ///
+    /// A missing key is created with `ttl`. An existing key keeps its deadline unless
+    /// `reset_ttl` is set, in which case it expires `ttl` from now. Tags are kept.
+    pub(crate) async fn update(
+        &self,
+        key: &K,
+        ttl: Option<Duration>,
+        reset_ttl: bool,
+        f: impl FnOnce(Option<&V>) -> V,
+    ) -> V {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let (value, expire_at, ttl) = match self.live_entry(&mut storage, key, now) {
+            Some(entry) if !reset_ttl => (f(Some(&entry.value)), entry.expire_at, entry.ttl),
+            current => {
+                let value = f(current.map(|entry| &entry.value));
+                let ttl = ttl.map(|d| self.jittered(d));
+                (value, ttl.map(|d| now + d), ttl)
+            }
+        };
+        let entry = self.new_entry(value.clone(), expire_at, ttl, now);
+        let evicted = storage.insert(key.clone(), entry);
+        self.spill(&mut storage, evicted, now);
+        value
+    }
+
+    /// Runs `f` on the value of `key` in place while holding the write lock.
+    ///
+    /// A missing key is created empty with the TTL in `create`, or left alone if
+    /// `create` is `None`. A collection left empty by `f` is removed, so an empty
+    /// collection and a missing key are the same thing.
+    pub(crate) async fn mutate<R>(
+        &self,
+        key: &K,
+        create: Option<Option<Duration>>,
+        f: impl FnOnce(&mut V) -> R,
+    ) -> Option<R>
+    where
+        V: Collection,
+    {
+        self.mutate_at(key, create, false, |value, _| f(value))
+            .await
+    }
+
+    /// Like [`mutate`](Self::mutate), but also passes `f` the time read under the lock,
+    /// and with `restart_ttl` restarts the TTL in `create` on every call rather than
+    /// only when the key is created.
+    pub(crate) async fn mutate_at<R>(
+        &self,
+        key: &K,
+        create: Option<Option<Duration>>,
+        restart_ttl: bool,
+        f: impl FnOnce(&mut V, Instant) -> R,
+    ) -> Option<R>
+    where
+        V: Collection,
+    {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let created = self.live_entry(&mut storage, key, now).is_none();
+        if created {
+            let ttl = create?.map(|d| self.jittered(d));
+            let entry = self.new_entry(V::default(), ttl.map(|d| now + d), ttl, now);
+            storage.untag(key);
+            let evicted = storage.insert(key.clone(), entry);
+            self.spill(&mut storage, evicted, now);
+        }
+        storage.versions += 1;
+        let version = storage.versions;
+        let entry = storage.map.get_mut(key)?;
+        let result = f(&mut entry.value, now);
+        // Any reload started before this change would overwrite it
+        entry.version = version;
+        if restart_ttl
+            && !created
+            && let Some(ttl) = create
+        {
+            let ttl = ttl.map(|d| self.jittered(d));
+            entry.expire_at = ttl.map(|d| now + d);
+            entry.ttl = ttl;
+        }
+        if entry.value.is_empty() {
+            storage.remove(key);
+        } else {
+            storage.touch(key);
+        }
+        Some(result)
+    }
+
+    /// Runs `f` on the live value of `key` without cloning it.
+    pub(crate) async fn inspect<R>(&self, key: &K, f: impl FnOnce(&V) -> R) -> Option<R> {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let result = f(&self.live_entry(&mut storage, key, now)?.value);
+        storage.touch(key);
+        Some(result)
+    }
+
+    /// Gives a live `key` a new TTL from now if `f` accepts its value, and returns
+    /// whether it did.
+    pub(crate) async fn expire_if(
+        &self,
+        key: &K,
+        ttl: Duration,
+        f: impl FnOnce(&V) -> bool,
+    ) -> bool {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        match self.live_entry(&mut storage, key, now) {
+            Some(entry) if f(&entry.value) => {
+                entry.expire_at = Some(now + ttl);
+                entry.ttl = Some(ttl);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes a live `key` from the cache if `f` accepts its value, and returns whether
+    /// it did. A backing store is not touched.
+    pub(crate) async fn remove_if(&self, key: &K, f: impl FnOnce(&V) -> bool) -> bool {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        match self.live_entry(&mut storage, key, now) {
+            Some(entry) if f(&entry.value) => {
+                storage.remove(key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces the value of a live `key`, keeping its TTL and tags, and returns the
+    /// previous value, the new entry's version and what it replaced. Does nothing if
+    /// the key is missing.
+    async fn swap(&self, key: &K, value: V) -> Option<(V, u64, Replaced<V>)> {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let entry = self.live_entry(&mut storage, key, now)?;
+        let (old, expire_at, ttl) = (entry.value.clone(), entry.expire_at, entry.ttl);
+        let entry = self.new_entry(value, expire_at, ttl, now);
+        let tags = storage.tagged.get(key).cloned().unwrap_or_default();
+        let (version, replaced) =
+            self.insert_replacing(&mut storage, key.clone(), entry, tags, now);
+        Some((old, version, replaced))
+    }
+
+    /// Writes `value` for `key` and returns the previous live value, the new entry's
+    /// version and what it replaced. A live key keeps its TTL and tags; an absent or
+    /// expired key is created without either.
+    async fn exchange(&self, key: &K, value: V) -> (Option<V>, u64, Replaced<V>) {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let (old, expire_at, ttl, tags) = match self.live_entry(&mut storage, key, now) {
+            Some(entry) => {
+                let (old, expire_at, ttl) = (entry.value.clone(), entry.expire_at, entry.ttl);
+                let tags = storage.tagged.get(key).cloned().unwrap_or_default();
+                (Some(old), expire_at, ttl, tags)
+            }
+            None => (None, None, None, Vec::new()),
+        };
+        let entry = self.new_entry(value, expire_at, ttl, now);
+        let (version, replaced) =
+            self.insert_replacing(&mut storage, key.clone(), entry, tags, now);
+        (old, version, replaced)
+    }
+
+    /// Removes a key from the cache manually.
+    ///
+    /// This immediately removes the key-value pair from the cache, regardless
+    /// of any TTL that may have been set. If a backing store is configured, the key
+    /// is also deleted from the store; use [`try_remove`](Self::try_remove) to see
+    /// whether that failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to remove
     ///
     /// # Examples
     ///
@@ -316,26 +1489,62 @@ where
     ///     let cache = MiniCache::new(Duration::from_secs(60));
     ///     
     ///     cache.set("key1", "value1", None).await;
-    ///     cache.set("key2", "value2", None).await;
+    ///     assert_eq!(cache.get(&"key1").await, Some("value1"));
     ///     
-    ///     println!("Cache has {} entries", cache.len().await);
+    ///     cache.remove(&"key1").await;
+    ///     assert_eq!(cache.get(&"key1").await, None);
     /// }
     /// ```
-    pub async fn len(&self) -> usize {
-        let map = self.inner.read().await;
-        map.iter()
-            .filter(|(_, (_, expire_at))| expire_at.map_or(true, |t| Instant::now() < t))
-            .count()
+    pub async fn remove(&self, key: &K) {
+        let _ = self.try_remove(key).await;
     }
 
-    /// Returns `true` if the cache contains no valid (non-expired) entries.
+    /// Removes a key like [`remove`](Self::remove), returning the error if a
+    /// write-through store rejects the delete.
     ///
-    /// This method is more efficient than calling `len() == 0` as it can
-    /// return early once a valid entry is found.
+    /// # Errors
+    ///
+    /// Returns the store's error. The key is already gone from the cache by then, but
+    /// a later [`get`](Self::get) may load it from the store again.
+    pub async fn try_remove(&self, key: &K) -> Result<(), StoreError> {
+        let Some(backing) = &self.backing else {
+            self.remove_cached(key).await;
+            return Ok(());
+        };
+        let _guard = backing.guard(key).await;
+        self.remove_cached(key).await;
+        backing.delete(key).await
+    }
+
+    /// Removes `key` from memory and from the disk tier, leaving the store untouched.
+    async fn remove_cached(&self, key: &K) {
+        let mut storage = self.inner.write().await;
+        storage.remove(key);
+        if let Some(tier) = &self.tier {
+            tier.remove(key);
+        }
+    }
+
+    /// Stores a key-value pair only if the key is absent (`SET NX`).
+    ///
+    /// Checking for the key and writing it happen atomically under the map lock, so of
+    /// several concurrent callers exactly one succeeds. Expired entries count as absent.
+    /// Presence is decided by the cache alone; a backing store is written through but
+    /// not consulted. The store write is ordered with other writes to the key as in
+    /// [`set`](Self::set), and a value the store rejects is rolled back from the cache;
+    /// use [`try_set_if_absent`](Self::try_set_if_absent) to tell a rejected write from
+    /// a present key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to store the value under
+    /// * `value` - The value to store
+    /// * `ttl` - Optional time-to-live for the entry
     ///
     /// # Returns
     ///
-    /// `true` if the cache is empty, `false` otherwise
+    /// `true` if the value was stored, `false` if the key was already present or the
+    /// store rejected the write
     ///
     /// # Examples
     ///
@@ -346,29 +1555,72 @@ where
     /// #[tokio::main]
     /// async fn main() {
     ///     let cache = MiniCache::new(Duration::from_secs(60));
-    ///     
-    ///     assert!(cache.is_empty().await);
-    ///     
-    ///     cache.set("key1", "value1", None).await;
-    ///     assert!(!cache.is_empty().await);
+    ///
+    ///     assert!(cache.set_if_absent("job:1", "worker-a", None).await);
+    ///     assert!(!cache.set_if_absent("job:1", "worker-b", None).await);
+    ///     assert_eq!(cache.get(&"job:1").await, Some("worker-a"));
     /// }
     /// ```
-    pub async fn is_empty(&self) -> bool {
-        let map = self.inner.read().await;
-        let now = Instant::now();
-        !map.iter()
-            .any(|(_, (_, expire_at))| expire_at.map_or(true, |t| now < t))
+    pub async fn set_if_absent(&self, key: K, value: V, ttl: Option<Duration>) -> bool {
+        self.write_if(key, value, ttl, false).await.unwrap_or(false)
     }
 
-    /// Returns a vector of all valid (non-expired) keys in the cache.
+    /// Stores a key-value pair only if the key is absent, like
+    /// [`set_if_absent`](Self::set_if_absent), returning the error if a write-through
+    /// store rejects the write.
     ///
-    /// This method iterates through all entries and collects keys for those
-    /// that haven't expired. The order of keys in the returned vector is
-    /// not guaranteed.
+    /// # Returns
+    ///
+    /// `Ok(true)` if the value was stored, `Ok(false)` if the key was already present
+    ///
+    /// # Errors
+    ///
+    /// Returns the store's error; the key is then left absent, unless another write
+    /// stored it in the meantime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{MemoryStore, MiniCache};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::builder(Duration::from_secs(60))
+    ///         .write_through(MemoryStore::new())
+    ///         .build();
+    ///
+    ///     assert_eq!(cache.try_set_if_absent("job:1", "worker-a", None).await.ok(), Some(true));
+    ///     assert_eq!(cache.try_set_if_absent("job:1", "worker-b", None).await.ok(), Some(false));
+    /// }
+    /// ```
+    pub async fn try_set_if_absent(
+        &self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<bool, StoreError> {
+        self.write_if(key, value, ttl, false).await
+    }
+
+    /// Stores a key-value pair only if the key is present (`SET XX`).
+    ///
+    /// The new value and TTL replace the old ones, and tags are cleared, as with
+    /// [`set`](Self::set). Expired entries count as absent. A value a backing store
+    /// rejects is rolled back from the cache, as with [`set`](Self::set); use
+    /// [`try_set_if_present`](Self::try_set_if_present) to tell a rejected write from an
+    /// absent key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to overwrite
+    /// * `value` - The new value
+    /// * `ttl` - Optional time-to-live for the entry
     ///
     /// # Returns
     ///
-    /// A vector containing all valid keys currently in the cache
+    /// `true` if the value was stored, `false` if the key was absent or the store
+    /// rejected the write
     ///
     /// # Examples
     ///
@@ -379,278 +1631,3015 @@ where
     /// #[tokio::main]
     /// async fn main() {
     ///     let cache = MiniCache::new(Duration::from_secs(60));
-    ///     
-    ///     cache.set("key1", "value1", None).await;
-    ///     cache.set("key2", "value2", None).await;
-    ///     
-    ///     let keys = cache.keys().await;
-    ///     println!("Cache keys: {:?}", keys);
+    ///
+    ///     assert!(!cache.set_if_present("session", "v2", None).await);
+    ///     cache.set("session", "v1", None).await;
+    ///     assert!(cache.set_if_present("session", "v2", None).await);
+    ///     assert_eq!(cache.get(&"session").await, Some("v2"));
     /// }
     /// ```
-    pub async fn keys(&self) -> Vec<K> {
-        let map = self.inner.read().await;
-        map.iter()
-            .filter_map(|(k, (_, expire_at))| {
-                if expire_at.map_or(true, |t| Instant::now() < t) {
-                    Some(k.clone())
-                } else {
-                    None
-                }
-            })
-            .collect()
+    pub async fn set_if_present(&self, key: K, value: V, ttl: Option<Duration>) -> bool {
+        self.write_if(key, value, ttl, true).await.unwrap_or(false)
+    }
+
+    /// Stores a key-value pair only if the key is present, like
+    /// [`set_if_present`](Self::set_if_present), returning the error if a
+    /// write-through store rejects the write.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(true)` if the value was stored, `Ok(false)` if the key was absent
+    ///
+    /// # Errors
+    ///
+    /// Returns the store's error; the key then keeps its previous entry, unless
+    /// another write replaced it in the meantime.
+    pub async fn try_set_if_present(
+        &self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<bool, StoreError> {
+        self.write_if(key, value, ttl, true).await
+    }
+
+    /// Writes `key` if its presence matches `present`, then writes the value through to
+    /// the backing store under the key's store guard, like [`write`](Self::write).
+    /// Returns whether the presence matched.
+    async fn write_if(
+        &self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+        present: bool,
+    ) -> Result<bool, StoreError> {
+        let guard = match &self.backing {
+            Some(backing) => Some(backing.guard(&key).await),
+            None => None,
+        };
+        let (version, replaced) = {
+            let mut storage = self.lock_key(&key).await;
+            let now = self.clock.now();
+            if self.live_entry(&mut storage, &key, now).is_some() != present {
+                return Ok(false);
+            }
+            let ttl = ttl.map(|d| self.jittered(d));
+            let entry = self.new_entry(value.clone(), ttl.map(|d| now + d), ttl, now);
+            self.insert_replacing(&mut storage, key.clone(), entry, Vec::new(), now)
+        };
+        let stored = self.store_through(&key, &value, version, replaced).await;
+        drop(guard);
+        stored.map(|()| true)
+    }
+
+    /// Stores a new value for a key and returns the previous value (`GETSET`).
+    ///
+    /// A present key keeps its TTL and tags. An absent or expired key is stored without
+    /// a TTL, and `None` is returned. If a write-through store rejects the write, the
+    /// previous entry is put back and `None` is returned; use
+    /// [`try_replace`](Self::try_replace) to see the error.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to update
+    /// * `value` - The new value
+    ///
+    /// # Returns
+    ///
+    /// `Some(old_value)` if the key was present, `None` if it was absent or the store
+    /// rejected the write
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     cache.set("config", 1, Some(Duration::from_secs(30))).await;
+    ///
+    ///     assert_eq!(cache.replace(&"config", 2).await, Some(1));
+    ///     assert_eq!(cache.get(&"config").await, Some(2));
+    ///     assert_eq!(cache.replace(&"missing", 3).await, None);
+    ///     assert_eq!(cache.get(&"missing").await, Some(3));
+    /// }
+    /// ```
+    pub async fn replace(&self, key: &K, value: V) -> Option<V> {
+        self.try_replace(key, value).await.unwrap_or(None)
+    }
+
+    /// Stores a new value for a key like [`replace`](Self::replace), returning the
+    /// error if a write-through store rejects the write.
+    ///
+    /// # Errors
+    ///
+    /// Returns the store's error; the key is then left as it was before the call,
+    /// unless another write replaced it in the meantime.
+    pub async fn try_replace(&self, key: &K, value: V) -> Result<Option<V>, StoreError> {
+        let Some(backing) = &self.backing else {
+            return Ok(self.exchange(key, value).await.0);
+        };
+        let _guard = backing.guard(key).await;
+        let (old, version, replaced) = self.exchange(key, value.clone()).await;
+        self.store_through(key, &value, version, replaced)
+            .await
+            .map(|()| old)
+    }
+
+    /// Removes a key and returns the value it had.
+    ///
+    /// Reading and removing happen atomically, so when several tasks race to take the
+    /// same key, exactly one of them gets the value. If a backing store is configured,
+    /// a removed key is also deleted from the store; if that delete fails, the entry
+    /// is put back and `None` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to take
+    ///
+    /// # Returns
+    ///
+    /// `Some(value)` if the key was present and not expired, `None` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     cache.set("token", "abc", None).await;
+    ///
+    ///     assert_eq!(cache.get_and_remove(&"token").await, Some("abc"));
+    ///     assert_eq!(cache.get_and_remove(&"token").await, None);
+    /// }
+    /// ```
+    pub async fn get_and_remove(&self, key: &K) -> Option<V> {
+        let guard = match &self.backing {
+            Some(backing) => Some(backing.guard(key).await),
+            None => None,
+        };
+        let entry = {
+            let mut storage = self.lock_key(key).await;
+            let now = self.clock.now();
+            self.live_entry(&mut storage, key, now)?;
+            storage.remove(key)?
+        };
+        let value = self.delete_taken(key, entry).await;
+        drop(guard);
+        value
+    }
+
+    /// Deletes a key just taken out of the cache from the backing store.
+    ///
+    /// If the store rejects the delete, the entry is put back unless the key has been
+    /// written since, and `None` is returned: a value is never handed out as taken
+    /// while the store still has it.
+    async fn delete_taken(&self, key: &K, entry: Entry<V>) -> Option<V> {
+        let Some(backing) = &self.backing else {
+            return Some(entry.value);
+        };
+        if backing.delete(key).await.is_ok() {
+            return Some(entry.value);
+        }
+        let mut storage = self.inner.write().await;
+        if !storage.map.contains_key(key) {
+            let now = self.clock.now();
+            self.insert(&mut storage, key.clone(), entry, now);
+        }
+        None
+    }
+
+    /// Writes a value just cached as `version` through to the backing store.
+    ///
+    /// If the store rejects it, what the write `replaced` is put back, unless the
+    /// entry has been replaced again since and so is no longer the one that failed.
+    async fn store_through(
+        &self,
+        key: &K,
+        value: &V,
+        version: u64,
+        replaced: Replaced<V>,
+    ) -> Result<(), StoreError> {
+        let Some(backing) = &self.backing else {
+            return Ok(());
+        };
+        let result = backing.store(key, value).await;
+        if result.is_err() {
+            let mut storage = self.inner.write().await;
+            if storage.map.get(key).is_some_and(|e| e.version == version) {
+                self.restore(&mut storage, key, replaced);
+            }
+        }
+        result
+    }
+
+    /// Removes every entry carrying `tag` from memory and from the disk tier.
+    ///
+    /// Like [`clear`](Self::clear), this only affects the cache; a backing store is not
+    /// touched.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag to invalidate
+    ///
+    /// # Returns
+    ///
+    /// The number of entries removed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set_tagged("page:1", "html", None, ["user:7"]).await;
+    ///     cache.set_tagged("page:2", "html", None, ["user:8"]).await;
+    ///
+    ///     cache.invalidate_tag("user:7").await;
+    ///     assert_eq!(cache.get(&"page:1").await, None);
+    ///     assert_eq!(cache.get(&"page:2").await, Some("html"));
+    /// }
+    /// ```
+    pub async fn invalidate_tag(&self, tag: &str) -> usize {
+        let mut storage = self.inner.write().await;
+        let keys: Vec<K> = storage
+            .tags
+            .get(tag)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default();
+        for key in &keys {
+            storage.remove(key);
+            if let Some(tier) = &self.tier {
+                tier.remove(key);
+            }
+        }
+        keys.len()
+    }
+
+    /// Removes all entries from the cache.
+    ///
+    /// This operation clears the entire cache, removing all key-value pairs
+    /// regardless of their expiration status. A backing store is not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     
+    ///     cache.set("key1", "value1", None).await;
+    ///     cache.set("key2", "value2", None).await;
+    ///     assert_eq!(cache.len().await, 2);
+    ///     
+    ///     cache.clear().await;
+    ///     assert_eq!(cache.len().await, 0);
+    /// }
+    /// ```
+    pub async fn clear(&self) {
+        let mut storage = self.inner.write().await;
+        storage.clear();
+        if let Some(tier) = &self.tier {
+            tier.clear();
+        }
+    }
+
+    /// Checks if a key exists in the cache and has not expired.
+    ///
+    /// This is equivalent to calling `get(key).is_some()` but doesn't return
+    /// the actual value, which can be more efficient for existence checks.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to check
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key exists and is not expired, `false` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     
+    ///     cache.set("key1", "value1", None).await;
+    ///     
+    ///     if cache.contains(&"key1").await {
+    ///         println!("Key exists!");
+    ///     }
+    /// }
+    /// ```
+    pub async fn contains(&self, key: &K) -> bool {
+        self.get(key).await.is_some()
+    }
+
+    /// Returns the number of valid (non-expired) entries in the cache.
+    ///
+    /// This method iterates through all entries and counts only those that
+    /// haven't expired. Note that this operation requires checking expiration
+    /// times, so it has O(n) complexity.
+    ///
+    /// # Returns
+    ///
+    /// The number of valid entries currently in the cache
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     
+    ///     cache.set("key1", "value1", None).await;
+    ///     cache.set("key2", "value2", None).await;
+    ///     
+    ///     println!("Cache has {} entries", cache.len().await);
+    /// }
+    /// ```
+    pub async fn len(&self) -> usize {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        let in_memory = storage.map.values().filter(|e| e.is_live(now)).count();
+        in_memory + self.tier.as_ref().map_or(0, |tier| tier.len(now))
+    }
+
+    /// Returns `true` if the cache contains no valid (non-expired) entries.
+    ///
+    /// This method is more efficient than calling `len() == 0` as it can
+    /// return early once a valid entry is found.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the cache is empty, `false` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     
+    ///     assert!(cache.is_empty().await);
+    ///     
+    ///     cache.set("key1", "value1", None).await;
+    ///     assert!(!cache.is_empty().await);
+    /// }
+    /// ```
+    pub async fn is_empty(&self) -> bool {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        !storage.map.values().any(|e| e.is_live(now))
+            && self.tier.as_ref().is_none_or(|tier| tier.len(now) == 0)
+    }
+
+    /// Returns a vector of all valid (non-expired) keys in the cache.
+    ///
+    /// This method iterates through all entries and collects keys for those
+    /// that haven't expired. The order of keys in the returned vector is
+    /// not guaranteed.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing all valid keys currently in the cache
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     
+    ///     cache.set("key1", "value1", None).await;
+    ///     cache.set("key2", "value2", None).await;
+    ///     
+    ///     let keys = cache.keys().await;
+    ///     println!("Cache keys: {:?}", keys);
+    /// }
+    /// ```
+    pub async fn keys(&self) -> Vec<K> {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        let mut keys: Vec<K> = storage
+            .map
+            .iter()
+            .filter_map(|(k, entry)| {
+                if entry.is_live(now) {
+                    Some(k.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if let Some(tier) = &self.tier {
+            keys.extend(tier.keys(now));
+        }
+        keys
+    }
+
+    /// Iterates over the cache incrementally, one bounded batch per call.
+    ///
+    /// Start with a cursor of `0` and pass the returned cursor to the next call until
+    /// it comes back as `0`. Each call holds the read lock only while it visits up to
+    /// `count` entries, so writers are never stalled for a whole-cache walk.
+    ///
+    /// Every entry that stays in the cache for the whole scan is returned exactly
+    /// once, even if it is overwritten meanwhile. Entries added during the scan may
+    /// or may not be returned. Expired entries are visited but not returned, so a
+    /// batch can hold fewer than `count` entries before the scan is over. Entries that
+    /// currently live in the disk tier are not visited.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - `0` to start a scan, or the cursor returned by the previous call
+    /// * `count` - The maximum number of entries to visit in this call, at least one
+    ///
+    /// # Returns
+    ///
+    /// The cursor for the next call, `0` once the scan is complete, and the batch of
+    /// entries
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     for i in 0..10 {
+    ///         cache.set(i, i * 10, None).await;
+    ///     }
+    ///
+    ///     let mut seen = Vec::new();
+    ///     let mut cursor = 0;
+    ///     loop {
+    ///         let (next, batch) = cache.scan(cursor, 3).await;
+    ///         seen.extend(batch);
+    ///         if next == 0 {
+    ///             break;
+    ///         }
+    ///         cursor = next;
+    ///     }
+    ///     assert_eq!(seen.len(), 10);
+    /// }
+    /// ```
+    pub async fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(K, V)>) {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        let mut batch = Vec::new();
+        let mut range = storage
+            .seqs
+            .range((Bound::Excluded(cursor), Bound::Unbounded))
+            .peekable();
+        let mut last = cursor;
+        for (&seq, key) in range.by_ref().take(count.max(1)) {
+            last = seq;
+            if let Some(entry) = storage.map.get(key).filter(|e| e.is_live(now)) {
+                batch.push((key.clone(), entry.value.clone()));
+            }
+        }
+        let next = if range.peek().is_some() { last } else { 0 };
+        (next, batch)
+    }
+
+    /// Returns a stream of the `(key, value)` pairs in the cache.
+    ///
+    /// The stream reads the cache in batches through [`scan`](Self::scan), so it never
+    /// holds the lock across an `.await` of the consumer. See [`Iter`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     cache.set("key1", "value1", None).await;
+    ///
+    ///     let entries: Vec<_> = cache.iter().collect().await;
+    ///     assert_eq!(entries, vec![("key1", "value1")]);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Iter<K, V> {
+        Iter::new(self.clone())
+    }
+
+    /// Returns a stream of the `(key, value)` pairs in the cache.
+    ///
+    /// Same as [`iter`](Self::iter).
+    pub fn entries(&self) -> Iter<K, V> {
+        self.iter()
+    }
+
+    /// Returns a stream of the values in the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     cache.set("key1", 10, None).await;
+    ///     cache.set("key2", 20, None).await;
+    ///
+    ///     let total: i32 = cache.values().fold(0, |acc, v| acc + v).await;
+    ///     assert_eq!(total, 30);
+    /// }
+    /// ```
+    pub fn values(&self) -> Values<K, V> {
+        Values::new(self.clone())
+    }
+
+    /// Returns the remaining time-to-live of a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to inspect
+    ///
+    /// # Returns
+    ///
+    /// `None` if the key doesn't exist or has expired, `Some(None)` if it never
+    /// expires, and `Some(Some(remaining))` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("permanent", "value", None).await;
+    ///     cache.set("temporary", "value", Some(Duration::from_secs(30))).await;
+    ///
+    ///     assert_eq!(cache.ttl(&"missing").await, None);
+    ///     assert_eq!(cache.ttl(&"permanent").await, Some(None));
+    ///
+    ///     let remaining = cache.ttl(&"temporary").await.flatten().unwrap();
+    ///     assert!(remaining <= Duration::from_secs(30));
+    /// }
+    /// ```
+    pub async fn ttl(&self, key: &K) -> Option<Option<Duration>> {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let entry = self.live_entry(&mut storage, key, now)?;
+        Some(entry.expire_at.map(|t| t - now))
+    }
+
+    /// Sets a new time-to-live on an existing key without rewriting its value.
+    ///
+    /// The key expires `ttl` from now, and `ttl` becomes the length that
+    /// [`touch`](Self::touch) resets to.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to update
+    /// * `ttl` - The new time-to-live, starting now
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key existed and was updated, `false` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("session", "data", None).await;
+    ///     assert!(cache.expire(&"session", Duration::from_secs(300)).await);
+    ///     assert!(cache.ttl(&"session").await.flatten().is_some());
+    /// }
+    /// ```
+    pub async fn expire(&self, key: &K, ttl: Duration) -> bool {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let version = storage.next_version();
+        let Some(entry) = self.live_entry(&mut storage, key, now) else {
+            return false;
+        };
+        entry.changed(version);
+        entry.expire_at = Some(now + ttl);
+        entry.ttl = Some(ttl);
+        true
+    }
+
+    /// Sets the absolute deadline of an existing key without rewriting its value.
+    ///
+    /// The time left until `deadline` becomes the length that
+    /// [`touch`](Self::touch) resets to. A deadline in the past expires the key
+    /// immediately. The deadline is compared against the cache's [`Clock`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to update
+    /// * `deadline` - The instant at which the key expires
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key existed and was updated, `false` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("report", "data", None).await;
+    ///     let deadline = Instant::now() + Duration::from_secs(3600);
+    ///     assert!(cache.expire_at(&"report", deadline).await);
+    /// }
+    /// ```
+    pub async fn expire_at(&self, key: &K, deadline: Instant) -> bool {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let version = storage.next_version();
+        let Some(entry) = self.live_entry(&mut storage, key, now) else {
+            return false;
+        };
+        entry.changed(version);
+        entry.expire_at = Some(deadline);
+        entry.ttl = Some(deadline.saturating_duration_since(now));
+        true
+    }
+
+    /// Removes the time-to-live of a key so it never expires.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to update
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key existed and was updated, `false` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("config", "data", Some(Duration::from_secs(5))).await;
+    ///     assert!(cache.persist(&"config").await);
+    ///     assert_eq!(cache.ttl(&"config").await, Some(None));
+    /// }
+    /// ```
+    pub async fn persist(&self, key: &K) -> bool {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let version = storage.next_version();
+        let Some(entry) = self.live_entry(&mut storage, key, now) else {
+            return false;
+        };
+        entry.changed(version);
+        entry.expire_at = None;
+        entry.ttl = None;
+        true
+    }
+
+    /// Restarts the time-to-live of a key at the length it was last given.
+    ///
+    /// Useful for sliding expiration, such as keeping a session alive while it is in
+    /// use. Keys without a TTL are left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to touch
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key exists, `false` otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("session", "data", Some(Duration::from_secs(1800))).await;
+    ///
+    ///     // Later, on activity: the session again has 30 minutes left
+    ///     assert!(cache.touch(&"session").await);
+    /// }
+    /// ```
+    pub async fn touch(&self, key: &K) -> bool {
+        let mut storage = self.lock_key(key).await;
+        let now = self.clock.now();
+        let version = storage.next_version();
+        let Some(entry) = self.live_entry(&mut storage, key, now) else {
+            return false;
+        };
+        entry.changed(version);
+        entry.expire_at = entry.ttl.map(|ttl| now + ttl);
+        true
+    }
+
+    /// Returns a handle to the namespace called `name`, creating it if needed.
+    ///
+    /// A namespace has the same API as the cache, but its keys are isolated from the
+    /// parent cache and from every other namespace. See [`Namespace`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the namespace
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.namespace("users").set(123, "alice", None).await;
+    ///
+    ///     assert_eq!(cache.namespace("users").get(&123).await, Some("alice"));
+    ///     assert_eq!(cache.get(&123).await, None);
+    /// }
+    /// ```
+    pub fn namespace(&self, name: &str) -> Namespace<K, V> {
+        self.namespace_with(name, NamespaceOptions::default())
+    }
+
+    /// Returns a handle to the namespace called `name`, creating it with `options` if
+    /// it does not exist yet.
+    ///
+    /// The options only take effect when the namespace is created; an existing
+    /// namespace keeps the settings it was created with.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the namespace
+    /// * `options` - Default TTL and capacity quota for a new namespace
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{MiniCache, NamespaceOptions};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     let pages = cache.namespace_with("pages", NamespaceOptions::new().capacity(1));
+    ///
+    ///     pages.set("/", "home", None).await;
+    ///     pages.set("/about", "about", None).await;
+    ///     assert_eq!(pages.len().await, 1);
+    /// }
+    /// ```
+    pub fn namespace_with(&self, name: &str, options: NamespaceOptions) -> Namespace<K, V> {
+        let mut namespaces = self.namespaces();
+        if let Some(namespace) = namespaces.get(name) {
+            return namespace.clone();
+        }
+        let mut builder = MiniCacheBuilder::new(self.cleanup_interval);
+        builder.capacity = options.capacity;
+        builder.early_expiration = self.early_expiration;
+        builder.jitter = self.jitter;
+        builder.seed = self.seed;
+        builder.clock = self.clock.clone();
+        builder.index = self.index;
+        let namespace = Namespace::new(name, builder.build(), options);
+        namespaces.insert(name.to_string(), namespace.clone());
+        namespace
+    }
+
+    /// Removes the namespace called `name` from the cache.
+    ///
+    /// A later [`namespace`](Self::namespace) call with the same name starts an empty
+    /// namespace. Handles that are still held keep working on the removed namespace,
+    /// whose entries and cleanup task are released once the last of them is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the namespace
+    ///
+    /// # Returns
+    ///
+    /// `true` if the namespace existed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     cache.namespace("import").set(1, "row", None).await;
+    ///
+    ///     assert!(cache.remove_namespace("import"));
+    ///     assert!(cache.namespace("import").is_empty().await);
+    /// }
+    /// ```
+    pub fn remove_namespace(&self, name: &str) -> bool {
+        self.namespaces().remove(name).is_some()
+    }
+
+    fn namespaces(&self) -> MutexGuard<'_, HashMap<String, Namespace<K, V>>> {
+        self.namespaces.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Publishes `message` on `channel` and returns how many subscribers received it.
+    ///
+    /// Messages go to the subscribers of `channel` and of every pattern that matches it.
+    /// Publishing never waits: it only buffers the message for each subscriber, and a
+    /// message published on a channel nobody listens to is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel to publish on
+    /// * `message` - The value delivered to each subscriber
+    ///
+    /// # Returns
+    ///
+    /// The number of subscriptions the message was delivered to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<&str, String> = MiniCache::new(Duration::from_secs(60));
+    ///     let mut orders = cache.subscribe("orders");
+    ///
+    ///     let delivered = cache.publish("orders", "order 17 shipped".to_string());
+    ///     assert_eq!(delivered, 1);
+    ///
+    ///     let message = orders.recv().await.unwrap();
+    ///     assert_eq!(message.payload, "order 17 shipped");
+    /// }
+    /// ```
+    pub fn publish(&self, channel: &str, message: V) -> usize {
+        self.broker.publish(channel, message)
+    }
+
+    /// Subscribes to the messages published on `channel`.
+    ///
+    /// Channels are independent of cache keys and exist while they have subscribers.
+    /// Only messages published after this call are received.
+    pub fn subscribe(&self, channel: &str) -> Subscription<V> {
+        self.broker.subscribe(channel)
+    }
+
+    /// Subscribes to the messages published on every channel matching the glob
+    /// `pattern`.
+    ///
+    /// Patterns use the syntax of [`keys_matching`](Self::keys_matching). Each message
+    /// records the pattern that matched in [`Message::pattern`](crate::Message::pattern).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<&str, u32> = MiniCache::new(Duration::from_secs(60));
+    ///     let mut users = cache.psubscribe("user:*");
+    ///
+    ///     cache.publish("user:42:login", 1);
+    ///     cache.publish("order:7", 2);
+    ///
+    ///     let message = users.recv().await.unwrap();
+    ///     assert_eq!(&*message.channel, "user:42:login");
+    ///     assert_eq!(message.pattern.as_deref(), Some("user:*"));
+    /// }
+    /// ```
+    pub fn psubscribe(&self, pattern: &str) -> Subscription<V> {
+        self.broker.psubscribe(pattern)
+    }
+
+    /// Returns the channels that currently have at least one direct subscriber.
+    pub fn channels(&self) -> Vec<String> {
+        self.broker.channels()
+    }
+
+    /// Writes every pending write-behind update to the backing store now, and waits
+    /// for the disk tier to write the entries spilled to it.
+    ///
+    /// Failed store writes are retried up to the configured limit. Does nothing unless
+    /// the cache was built with [`MiniCacheBuilder::write_behind`] or
+    /// [`MiniCacheBuilder::disk_tier`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the last store error if any write had to be dropped after exhausting
+    /// its retries, or the I/O error of the disk tier's last write if it failed. The
+    /// disk tier keeps entries it could not write in memory and retries them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{MemoryStore, MiniCache, WriteBehind};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = MemoryStore::new();
+    ///     let cache = MiniCache::builder(Duration::from_secs(60))
+    ///         .write_behind(store.clone(), WriteBehind::new(Duration::from_secs(5)))
+    ///         .build();
+    ///
+    ///     cache.set("key1", "value1", None).await;
+    ///     assert_eq!(store.get(&"key1"), None);
+    ///
+    ///     cache.flush().await.unwrap();
+    ///     assert_eq!(store.get(&"key1"), Some("value1"));
+    /// }
+    /// ```
+    pub async fn flush(&self) -> Result<(), StoreError> {
+        if let Some(backing) = &self.backing {
+            backing.flush().await?;
+        }
+        self.flush_tier().await
+    }
+
+    /// Waits on the blocking thread pool for the disk tier to write what it has queued.
+    async fn flush_tier(&self) -> Result<(), StoreError> {
+        if let Some(tier) = self.tier.clone() {
+            tokio::task::spawn_blocking(move || tier.flush()).await??;
+        }
+        Ok(())
+    }
+
+    /// Stops the write-behind flush task and flushes all pending writes.
+    ///
+    /// Call this before the application exits so no queued write is lost. The cache
+    /// stays usable afterwards, but later writes are only sent by explicit
+    /// [`flush`](Self::flush) calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns the last store error if any write had to be dropped after exhausting
+    /// its retries, or the I/O error of the disk tier's last write if it failed.
+    pub async fn shutdown(&self) -> Result<(), StoreError> {
+        if let Some(backing) = &self.backing {
+            backing.shutdown().await?;
+        }
+        self.flush_tier().await
+    }
+}
+
+impl<K, V> MiniCache<K, V>
+where
+    K: AsRef<str> + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Returns the valid (non-expired) keys accepted by `pred`, cloning only those.
+    async fn keys_where(&self, pred: impl Fn(&str) -> bool) -> Vec<K> {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        let mut keys: Vec<K> = storage
+            .map
+            .iter()
+            .filter(|(k, entry)| entry.is_live(now) && pred(k.as_ref()))
+            .map(|(k, _)| k.clone())
+            .collect();
+        if let Some(tier) = &self.tier {
+            keys.extend(tier.keys(now).into_iter().filter(|k| pred(k.as_ref())));
+        }
+        keys
+    }
+
+    /// Returns all valid (non-expired) keys that start with `prefix`.
+    ///
+    /// Caches built with [`MiniCacheBuilder::ordered`] seek to `prefix` in the sorted
+    /// key index and only visit matching keys, which they return in ascending order.
+    /// Other caches visit every key and return the matches in no particular order.
+    /// Keys held in the disk tier come last either way.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The prefix to look for
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("user:1", "alice", None).await;
+    ///     cache.set("user:2", "bob", None).await;
+    ///     cache.set("order:1", "lamp", None).await;
+    ///
+    ///     let mut keys = cache.scan_prefix("user:").await;
+    ///     keys.sort();
+    ///     assert_eq!(keys, vec!["user:1", "user:2"]);
+    /// }
+    /// ```
+    pub async fn scan_prefix(&self, prefix: &str) -> Vec<K>
+    where
+        K: Ord + Borrow<str>,
+    {
+        let storage = self.inner.read().await;
+        let Some(index) = storage.index.as_ref().map(|index| index.as_set()) else {
+            drop(storage);
+            return self.keys_where(|k| k.starts_with(prefix)).await;
+        };
+        let now = self.clock.now();
+        let mut keys: Vec<K> = index
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|k| (*k).borrow().starts_with(prefix))
+            .filter(|k| storage.map.get::<K>(k).is_some_and(|e| e.is_live(now)))
+            .cloned()
+            .collect();
+        if let Some(tier) = &self.tier {
+            keys.extend(
+                tier.keys(now)
+                    .into_iter()
+                    .filter(|k| k.as_ref().starts_with(prefix)),
+            );
+        }
+        keys
+    }
+
+    /// Returns all valid (non-expired) keys matching the glob `pattern`, in no
+    /// particular order.
+    ///
+    /// `*` matches any sequence of characters, `?` matches one character, `[abc]`,
+    /// `[a-z]` and `[!abc]` match character classes, and `\` escapes the next
+    /// character.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The glob pattern keys must match
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("user:1:name", "alice", None).await;
+    ///     cache.set("user:1:email", "alice@example.com", None).await;
+    ///     cache.set("user:2:name", "bob", None).await;
+    ///
+    ///     let mut keys = cache.keys_matching("user:*:name").await;
+    ///     keys.sort();
+    ///     assert_eq!(keys, vec!["user:1:name", "user:2:name"]);
+    /// }
+    /// ```
+    pub async fn keys_matching(&self, pattern: &str) -> Vec<K> {
+        self.keys_where(|k| glob_match(pattern, k)).await
+    }
+
+    /// Removes every key matching the glob `pattern`, as [`remove`](Self::remove)
+    /// would.
+    ///
+    /// All matching keys leave the cache under a single write lock, so no reader sees
+    /// only some of them gone. The deletes are then sent to a backing store key by key;
+    /// a key written again in the meantime keeps its new value in the store. The
+    /// pattern syntax is the one of [`keys_matching`](Self::keys_matching).
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The glob pattern keys must match
+    ///
+    /// # Returns
+    ///
+    /// The number of keys removed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.set("page:/home", "html", None).await;
+    ///     cache.set("page:/about", "html", None).await;
+    ///     cache.set("user:1", "alice", None).await;
+    ///
+    ///     assert_eq!(cache.remove_matching("page:*").await, 2);
+    ///     assert_eq!(cache.keys().await, vec!["user:1"]);
+    /// }
+    /// ```
+    pub async fn remove_matching(&self, pattern: &str) -> usize {
+        let keys = {
+            let mut storage = self.inner.write().await;
+            let now = self.clock.now();
+            let mut keys: Vec<K> = storage
+                .map
+                .iter()
+                .filter(|(k, entry)| entry.is_live(now) && glob_match(pattern, k.as_ref()))
+                .map(|(k, _)| k.clone())
+                .collect();
+            for key in &keys {
+                storage.remove(key);
+            }
+            if let Some(tier) = &self.tier {
+                let spilled: Vec<K> = tier
+                    .keys(now)
+                    .into_iter()
+                    .filter(|k| glob_match(pattern, k.as_ref()))
+                    .collect();
+                for key in &spilled {
+                    tier.remove(key);
+                }
+                keys.extend(spilled);
+            }
+            keys
+        };
+        if let Some(backing) = &self.backing {
+            for key in &keys {
+                let _guard = backing.guard(key).await;
+                if !self.inner.read().await.map.contains_key(key) {
+                    let _ = backing.delete(key).await;
+                }
+            }
+        }
+        keys.len()
+    }
+}
+
+impl<K, V> MiniCache<K, V>
+where
+    K: Ord + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Returns the live entries whose keys fall within `range`, in ascending key order.
+    ///
+    /// Works on any cache with `Ord` keys; caches built with
+    /// [`MiniCacheBuilder::ordered`] answer from a sorted index instead of sorting all
+    /// keys. Expired entries are skipped, and entries that currently live in the disk
+    /// tier are not included.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The key range, such as `a..b`, `a..=b` or `a..`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::builder(Duration::from_secs(60)).ordered().build();
+    ///
+    ///     cache.set(("eu", 2), "b", None).await;
+    ///     cache.set(("eu", 1), "a", None).await;
+    ///     cache.set(("us", 1), "c", None).await;
+    ///
+    ///     let europe = cache.range(("eu", 0)..("eu", u32::MAX)).await;
+    ///     assert_eq!(europe, vec![(("eu", 1), "a"), (("eu", 2), "b")]);
+    /// }
+    /// ```
+    pub async fn range<R>(&self, range: R) -> Vec<(K, V)>
+    where
+        R: RangeBounds<K>,
+    {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        storage
+            .sorted_keys(range.start_bound(), range.end_bound())
+            .filter_map(|k| {
+                let entry = storage.map.get(k).filter(|e| e.is_live(now))?;
+                Some((k.clone(), entry.value.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns the live entry with the smallest key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::builder(Duration::from_secs(60)).ordered().build();
+    ///
+    ///     cache.set(2, "b", None).await;
+    ///     cache.set(1, "a", None).await;
+    ///
+    ///     assert_eq!(cache.first().await, Some((1, "a")));
+    ///     assert_eq!(cache.last().await, Some((2, "b")));
+    /// }
+    /// ```
+    pub async fn first(&self) -> Option<(K, V)> {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        let mut keys = storage.sorted_keys(Bound::Unbounded, Bound::Unbounded);
+        keys.find_map(|k| live_pair(&storage, k, now))
+    }
+
+    /// Returns the live entry with the largest key.
+    pub async fn last(&self) -> Option<(K, V)> {
+        let storage = self.inner.read().await;
+        let now = self.clock.now();
+        let keys = storage.sorted_keys(Bound::Unbounded, Bound::Unbounded);
+        keys.rev().find_map(|k| live_pair(&storage, k, now))
+    }
+
+    /// Removes and returns the live entry with the smallest key.
+    ///
+    /// The key is removed as [`remove`](Self::remove) would, including from a backing
+    /// store. If the store rejects the delete, the entry is put back and `None` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::builder(Duration::from_secs(60)).ordered().build();
+    ///
+    ///     cache.set(1_700_000_002, "second", None).await;
+    ///     cache.set(1_700_000_001, "first", None).await;
+    ///
+    ///     assert_eq!(cache.pop_first().await, Some((1_700_000_001, "first")));
+    ///     assert_eq!(cache.len().await, 1);
+    /// }
+    /// ```
+    pub async fn pop_first(&self) -> Option<(K, V)> {
+        self.pop(false).await
+    }
+
+    /// Removes and returns the live entry with the largest key.
+    ///
+    /// The key is removed as [`remove`](Self::remove) would, including from a backing
+    /// store. If the store rejects the delete, the entry is put back and `None` is
+    /// returned.
+    pub async fn pop_last(&self) -> Option<(K, V)> {
+        self.pop(true).await
+    }
+
+    async fn pop(&self, last: bool) -> Option<(K, V)> {
+        let mut storage = self.inner.write().await;
+        let now = self.clock.now();
+        let (key, _) = {
+            let mut keys = storage.sorted_keys(Bound::Unbounded, Bound::Unbounded);
+            if last {
+                keys.rev().find_map(|k| live_pair(&storage, k, now))
+            } else {
+                keys.find_map(|k| live_pair(&storage, k, now))
+            }
+        }?;
+        let entry = storage.remove(&key)?;
+        drop(storage);
+        let value = self.delete_taken(&key, entry).await?;
+        Some((key, value))
+    }
+}
+
+/// Clones the entry for `key` if it is live.
+fn live_pair<K: Hash + Eq + Clone, V: Clone>(
+    storage: &Storage<K, V>,
+    key: &K,
+    now: Instant,
+) -> Option<(K, V)> {
+    let entry = storage.map.get(key).filter(|e| e.is_live(now))?;
+    Some((key.clone(), entry.value.clone()))
+}
+
+impl<K> MiniCache<K, i64>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Atomically adds `delta` to the counter at `key` and returns the new value.
+    ///
+    /// A missing key starts at zero and is created with `ttl`; an existing key keeps
+    /// its TTL. The addition saturates at the bounds of `i64`. Counters live in the
+    /// cache only; a backing store is neither read nor written.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The counter to update
+    /// * `delta` - The amount to add, which may be negative
+    /// * `ttl` - The time-to-live given to the key if it is created
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///     let window = Some(Duration::from_secs(60));
+    ///
+    ///     assert_eq!(cache.incr(&"requests:alice", 1, window).await, 1);
+    ///     assert_eq!(cache.incr(&"requests:alice", 1, window).await, 2);
+    ///     assert_eq!(cache.decr(&"requests:alice", 2, window).await, 0);
+    /// }
+    /// ```
+    pub async fn incr(&self, key: &K, delta: i64, ttl: Option<Duration>) -> i64 {
+        self.update(key, ttl, false, |n| {
+            n.copied().unwrap_or(0).saturating_add(delta)
+        })
+        .await
+    }
+
+    /// Atomically subtracts `delta` from the counter at `key` and returns the new value.
+    ///
+    /// Behaves like [`incr`](Self::incr) with the sign of `delta` flipped.
+    pub async fn decr(&self, key: &K, delta: i64, ttl: Option<Duration>) -> i64 {
+        self.update(key, ttl, false, |n| {
+            n.copied().unwrap_or(0).saturating_sub(delta)
+        })
+        .await
+    }
+
+    /// Atomically adds `delta` to the counter at `key` and restarts its time-to-live.
+    ///
+    /// Unlike [`incr`](Self::incr), the key expires `ttl` from now whether or not it
+    /// existed, which suits counters that should live as long as they keep changing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.incr(&"active", 1, Some(Duration::from_secs(5))).await;
+    ///     cache.incr_and_expire(&"active", 1, Duration::from_secs(300)).await;
+    ///
+    ///     let remaining = cache.ttl(&"active").await.flatten().unwrap();
+    ///     assert!(remaining > Duration::from_secs(5));
+    /// }
+    /// ```
+    pub async fn incr_and_expire(&self, key: &K, delta: i64, ttl: Duration) -> i64 {
+        self.update(key, Some(ttl), true, |n| {
+            n.copied().unwrap_or(0).saturating_add(delta)
+        })
+        .await
+    }
+
+    /// Atomically resets the counter at `key` to zero and returns its previous value.
+    ///
+    /// The key keeps its TTL. Returns `None`, without creating the key, if it does not
+    /// exist.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.incr(&"errors", 3, None).await;
+    ///     assert_eq!(cache.get_and_reset(&"errors").await, Some(3));
+    ///     assert_eq!(cache.get(&"errors").await, Some(0));
+    /// }
+    /// ```
+    pub async fn get_and_reset(&self, key: &K) -> Option<i64> {
+        self.swap(key, 0).await.map(|(old, ..)| old)
+    }
+}
+
+impl<K> MiniCache<K, f64>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Atomically adds `delta` to the floating-point counter at `key` and returns the
+    /// new value.
+    ///
+    /// A missing key starts at zero and is created with `ttl`; an existing key keeps
+    /// its TTL. Counters live in the cache only; a backing store is neither read nor
+    /// written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::MiniCache;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.incr_float(&"latency_ms", 12.5, None).await;
+    ///     assert_eq!(cache.incr_float(&"latency_ms", 7.5, None).await, 20.0);
+    /// }
+    /// ```
+    pub async fn incr_float(&self, key: &K, delta: f64, ttl: Option<Duration>) -> f64 {
+        self.update(key, ttl, false, |n| n.copied().unwrap_or(0.0) + delta)
+            .await
+    }
+
+    /// Atomically resets the floating-point counter at `key` to zero and returns its
+    /// previous value.
+    ///
+    /// The key keeps its TTL. Returns `None`, without creating the key, if it does not
+    /// exist.
+    pub async fn get_and_reset(&self, key: &K) -> Option<f64> {
+        self.swap(key, 0.0).await.map(|(old, ..)| old)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    /// Lets tasks spawned by the cache, such as background reloads, run to completion.
+    async fn settle() {
+        for _ in 0..8 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_basic_set_and_get() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        cache.set("key1", "value1", None).await;
+        let result = cache.get(&"key1").await;
+
+        assert_eq!(result, Some("value1"));
+    }
+
+    #[tokio::test]
+    async fn test_get_nonexistent_key() {
+        let cache: MiniCache<&str, &str> = MiniCache::new(Duration::from_secs(1));
+        let result = cache.get(&"nonexistent").await;
+
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_expiration() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_millis(100))
+            .clock(clock.clone())
+            .build();
+
+        // Set with 50ms TTL
+        cache
+            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .await;
+
+        // Should exist immediately
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+
+        clock.advance(Duration::from_millis(100));
+
+        // Should be expired
+        assert_eq!(cache.get(&"key1").await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_ttl_persistence() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_millis(100))
+            .clock(clock.clone())
+            .build();
+
+        cache.set("key1", "value1", None).await;
+
+        // Let the cleanup task run a few times
+        clock.advance(Duration::from_millis(200));
+        sleep(Duration::from_millis(200)).await;
+
+        // Should still exist (no TTL)
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_value() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        cache.set("key1", "value1", None).await;
+        cache.set("key1", "value2", None).await;
+
+        assert_eq!(cache.get(&"key1").await, Some("value2"));
+    }
+
+    #[tokio::test]
+    async fn test_remove() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        cache.set("key1", "value1", None).await;
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+
+        cache.remove(&"key1").await;
+        assert_eq!(cache.get(&"key1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_clear() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        cache.set("key1", "value1", None).await;
+        cache.set("key2", "value2", None).await;
+
+        assert_eq!(cache.len().await, 2);
+
+        cache.clear().await;
+
+        assert_eq!(cache.len().await, 0);
+        assert_eq!(cache.get(&"key1").await, None);
+        assert_eq!(cache.get(&"key2").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_contains() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        assert!(!cache.contains(&"key1").await);
+
+        cache.set("key1", "value1", None).await;
+        assert!(cache.contains(&"key1").await);
+
+        cache.remove(&"key1").await;
+        assert!(!cache.contains(&"key1").await);
+    }
+
+    #[tokio::test]
+    async fn test_contains_with_expired_key() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_millis(100))
+            .clock(clock.clone())
+            .build();
+
+        cache
+            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .await;
+        assert!(cache.contains(&"key1").await);
+
+        clock.advance(Duration::from_millis(100));
+        assert!(!cache.contains(&"key1").await);
+    }
+
+    #[tokio::test]
+    async fn test_len() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(cache.len().await, 0);
+
+        cache.set("key1", "value1", None).await;
+        assert_eq!(cache.len().await, 1);
+
+        cache.set("key2", "value2", None).await;
+        assert_eq!(cache.len().await, 2);
+
+        cache.remove(&"key1").await;
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_len_with_expired_items() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_millis(100))
+            .clock(clock.clone())
+            .build();
+
+        cache
+            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .await;
+        cache.set("key2", "value2", None).await;
+
+        assert_eq!(cache.len().await, 2);
+
+        clock.advance(Duration::from_millis(100));
+
+        // Only key2 should remain (key1 expired)
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_keys() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        cache.set("key1", "value1", None).await;
+        cache.set("key2", "value2", None).await;
+        cache.set("key3", "value3", None).await;
+
+        let mut keys = cache.keys().await;
+        keys.sort();
+
+        assert_eq!(keys, vec!["key1", "key2", "key3"]);
+    }
+
+    #[tokio::test]
+    async fn test_keys_with_expired_items() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_millis(100))
+            .clock(clock.clone())
+            .build();
+
+        cache
+            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .await;
+        cache.set("key2", "value2", None).await;
+        cache.set("key3", "value3", None).await;
+
+        clock.advance(Duration::from_millis(100));
+
+        let mut keys = cache.keys().await;
+        keys.sort();
+
+        // Only key2 and key3 should remain (key1 expired)
+        assert_eq!(keys, vec!["key2", "key3"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_automatic_cleanup() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_millis(50))
+            .clock(clock.clone())
+            .build();
+
+        cache
+            .set("key1", "value1", Some(Duration::from_millis(25)))
+            .await;
+        cache.set("key2", "value2", None).await;
+
+        // Let the cleanup task run once the entry has expired
+        clock.advance(Duration::from_millis(100));
+        sleep(Duration::from_millis(100)).await;
+
+        // Expired item should be cleaned up without being read
+        assert_eq!(cache.inner.read().await.map.len(), 1);
+        assert_eq!(cache.get(&"key1").await, None);
+        assert_eq!(cache.get(&"key2").await, Some("value2"));
+    }
+
+    #[tokio::test]
+    async fn test_different_key_types() {
+        let cache: MiniCache<i32, String> = MiniCache::new(Duration::from_secs(1));
+
+        cache.set(1, "value1".to_string(), None).await;
+        cache.set(2, "value2".to_string(), None).await;
+
+        assert_eq!(cache.get(&1).await, Some("value1".to_string()));
+        assert_eq!(cache.get(&2).await, Some("value2".to_string()));
+        assert_eq!(cache.get(&3).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_access() {
+        let cache = Arc::new(MiniCache::new(Duration::from_secs(1)));
+        let mut handles = vec![];
+
+        // Spawn multiple tasks that write to cache
+        for i in 0..10 {
+            let cache_clone = cache.clone();
+            let handle = tokio::spawn(async move {
+                cache_clone.set(i, format!("value{}", i), None).await;
+            });
+            handles.push(handle);
+        }
+
+        // Wait for all writes to complete
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // Verify all values are present
+        for i in 0..10 {
+            assert_eq!(cache.get(&i).await, Some(format!("value{}", i)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_clone() {
+        let cache1 = MiniCache::new(Duration::from_secs(1));
+        let cache2 = cache1.clone();
+
+        cache1.set("key1", "value1", None).await;
+
+        // Both caches should see the same data (shared inner state)
+        assert_eq!(cache2.get(&"key1").await, Some("value1"));
+
+        cache2.set("key2", "value2", None).await;
+        assert_eq!(cache1.get(&"key2").await, Some("value2"));
+    }
+
+    #[tokio::test]
+    async fn test_capacity_evicts_least_recently_used() {
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .capacity(2)
+            .build();
+
+        cache.set("key1", "value1", None).await;
+        cache.set("key2", "value2", None).await;
+
+        // Reading key1 makes key2 the least recently used entry
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+        cache.set("key3", "value3", None).await;
+
+        assert_eq!(cache.get(&"key2").await, None);
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+        assert_eq!(cache.get(&"key3").await, Some("value3"));
+        assert_eq!(cache.len().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_disk_tier_spills_and_promotes() {
+        let dir = crate::disk::TempDir::new("core-tier");
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .capacity(1)
+            .disk_tier(crate::DiskTier::open(dir.path()).unwrap())
+            .build();
+
+        cache.set(1, "value1".to_string(), None).await;
+        cache.set(2, "value2".to_string(), None).await;
+
+        let mut keys = cache.keys().await;
+        keys.sort();
+        assert_eq!(keys, vec![1, 2]);
+
+        // Promoting key 1 spills key 2 in its place
+        assert_eq!(cache.get(&1).await, Some("value1".to_string()));
+        assert_eq!(cache.get(&2).await, Some("value2".to_string()));
+        assert_eq!(cache.len().await, 2);
+
+        cache.remove(&1).await;
+        assert_eq!(cache.get(&1).await, None);
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disk_tier_honors_ttl() {
+        let dir = crate::disk::TempDir::new("core-ttl");
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .capacity(1)
+            .disk_tier(crate::DiskTier::open(dir.path()).unwrap())
+            .build();
+
+        cache
+            .set(1, "value1".to_string(), Some(Duration::from_millis(50)))
+            .await;
+        cache.set(2, "value2".to_string(), None).await;
+        assert_eq!(cache.len().await, 2);
+
+        clock.advance(Duration::from_millis(100));
+
+        assert_eq!(cache.len().await, 1);
+        assert_eq!(cache.get(&1).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_write_through_store() {
+        let store = crate::MemoryStore::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
+
+        cache.set("key1", "value1", None).await;
+        assert_eq!(store.get(&"key1"), Some("value1"));
+
+        cache.remove(&"key1").await;
+        assert_eq!(store.get(&"key1"), None);
+    }
+
+    /// A `MemoryStore` whose operations all fail while `down` is set.
+    #[derive(Clone, Default)]
+    struct DownStore {
+        inner: crate::MemoryStore<&'static str, &'static str>,
+        down: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl DownStore {
+        fn check(&self) -> Result<(), StoreError> {
+            if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err("store unavailable".into());
+            }
+            Ok(())
+        }
+
+        fn set_down(&self, down: bool) {
+            self.down.store(down, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl crate::Store<&'static str, &'static str> for DownStore {
+        async fn load(&self, key: &&'static str) -> Result<Option<&'static str>, StoreError> {
+            self.check()?;
+            self.inner.load(key).await
+        }
+
+        async fn store(&self, key: &&'static str, value: &&'static str) -> Result<(), StoreError> {
+            self.check()?;
+            self.inner.store(key, value).await
+        }
+
+        async fn delete(&self, key: &&'static str) -> Result<(), StoreError> {
+            self.check()?;
+            self.inner.delete(key).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_through_failures_are_reported() {
+        let store = DownStore::default();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
+        cache.set("key", "old", None).await;
+
+        store.set_down(true);
+        assert!(cache.try_set("key", "new", None).await.is_err());
+        assert!(cache.try_get(&"other").await.is_err());
+        assert!(!cache.set_if_present("key", "new", None).await);
+        assert_eq!(cache.get(&"key").await, Some("old"));
+        assert_eq!(store.inner.get(&"key"), Some("old"));
+
+        store.set_down(false);
+        cache.set("key", "value", None).await;
+        store.set_down(true);
+        assert!(cache.try_remove(&"key").await.is_err());
+        assert_eq!(store.inner.get(&"key"), Some("value"));
+
+        // A take that cannot reach the store leaves the value where it was
+        store.set_down(false);
+        cache.set("key", "value", None).await;
+        store.set_down(true);
+        assert_eq!(cache.get_and_remove(&"key").await, None);
+        assert_eq!(cache.get(&"key").await, Some("value"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_conditional_set_is_rolled_back() {
+        let store = DownStore::default();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
+
+        store.set_down(true);
+        assert!(!cache.set_if_absent("key", "first", None).await);
+        assert!(!cache.contains(&"key").await);
+
+        store.set_down(false);
+        assert!(cache.set_if_absent("key", "second", None).await);
+        assert_eq!(store.inner.get(&"key"), Some("second"));
+
+        // A rejected overwrite puts the previous value back, so the key stays present
+        store.set_down(true);
+        assert!(!cache.set_if_present("key", "third", None).await);
+        store.set_down(false);
+        assert!(!cache.set_if_absent("key", "fourth", None).await);
+        assert_eq!(cache.get(&"key").await, Some("second"));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_set_reports_store_errors() {
+        let store = DownStore::default();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
+
+        store.set_down(true);
+        assert!(cache.try_set_if_absent("key", "first", None).await.is_err());
+        assert_eq!(
+            cache.try_set_if_present("key", "first", None).await.ok(),
+            Some(false)
+        );
+
+        store.set_down(false);
+        assert_eq!(
+            cache.try_set_if_absent("key", "second", None).await.ok(),
+            Some(true)
+        );
+        assert_eq!(
+            cache.try_set_if_absent("key", "third", None).await.ok(),
+            Some(false)
+        );
+
+        store.set_down(true);
+        assert!(
+            cache
+                .try_set_if_present("key", "third", None)
+                .await
+                .is_err()
+        );
+        assert_eq!(cache.get(&"key").await, Some("second"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_replace_keeps_previous_entry() {
+        let store = DownStore::default();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
+        cache
+            .set_tagged("key", "old", Some(Duration::from_secs(10)), ["tag"])
+            .await;
+
+        store.set_down(true);
+        assert!(cache.try_replace(&"key", "new").await.is_err());
+        assert_eq!(cache.replace(&"key", "new").await, None);
+        assert_eq!(cache.replace(&"missing", "new").await, None);
+        assert!(!cache.contains(&"missing").await);
+        assert_eq!(cache.get(&"key").await, Some("old"));
+        assert_eq!(store.inner.get(&"key"), Some("old"));
+
+        store.set_down(false);
+        assert_eq!(
+            cache.try_replace(&"key", "new").await.ok(),
+            Some(Some("old"))
+        );
+        assert_eq!(cache.try_replace(&"missing", "new").await.ok(), Some(None));
+        assert_eq!(store.inner.get(&"missing"), Some("new"));
+        assert_eq!(cache.invalidate_tag("tag").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_overwrite_keeps_previous_entry() {
+        let clock = MockClock::new();
+        let store = DownStore::default();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .write_through(store.clone())
+            .build();
+        cache
+            .set_tagged("key", "old", Some(Duration::from_secs(10)), ["tag"])
+            .await;
+        clock.advance(Duration::from_secs(4));
+
+        store.set_down(true);
+        assert!(cache.try_set("key", "new", None).await.is_err());
+
+        assert_eq!(cache.get(&"key").await, Some("old"));
+        assert_eq!(cache.ttl(&"key").await, Some(Some(Duration::from_secs(6))));
+        assert_eq!(cache.invalidate_tag("tag").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_loads_from_store_on_miss() {
+        let store = crate::MemoryStore::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
+
+        cache.set("key1", "value1", None).await;
+        cache.clear().await;
+        assert_eq!(cache.len().await, 0);
+
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_behind_flushes_on_shutdown() {
+        let store = crate::MemoryStore::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_behind(
+                store.clone(),
+                crate::WriteBehind::new(Duration::from_secs(60)),
+            )
+            .build();
+
+        cache.set("key1", "value1", None).await;
+        cache.set("key2", "value2", None).await;
+        cache.remove(&"key2").await;
+        assert!(store.is_empty());
+
+        // A pending delete must not be undone by loading the old value
+        assert_eq!(cache.get(&"key2").await, None);
+
+        cache.shutdown().await.unwrap();
+        assert_eq!(store.get(&"key1"), Some("value1"));
+        assert_eq!(store.get(&"key2"), None);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_ahead_reloads_in_background() {
+        let clock = MockClock::new();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let loader_calls = calls.clone();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .refresh_ahead(Duration::from_millis(50), move |key: &'static str| {
+                loader_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async move { Ok::<_, crate::LoaderError>(format!("{key}-reloaded")) }
+            })
+            .build();
+
+        cache
+            .set("key1", "original".to_string(), Some(Duration::from_secs(5)))
+            .await;
+        clock.advance(Duration::from_millis(80));
+
+        // Past the refresh point: the current value is served and one reload starts
+        assert_eq!(cache.get(&"key1").await, Some("original".to_string()));
+        assert_eq!(cache.get(&"key1").await, Some("original".to_string()));
+        settle().await;
+
+        assert_eq!(cache.get(&"key1").await, Some("key1-reloaded".to_string()));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_change_survives_running_reload() {
+        let clock = MockClock::new();
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let loader_gate = gate.clone();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .refresh_ahead(Duration::from_millis(50), move |key: &'static str| {
+                let gate = loader_gate.clone();
+                async move {
+                    gate.notified().await;
+                    Ok::<_, crate::LoaderError>(format!("{key}-reloaded"))
+                }
+            })
+            .build();
+
+        cache
+            .set("key1", "original".to_string(), Some(Duration::from_secs(5)))
+            .await;
+        clock.advance(Duration::from_millis(80));
+
+        // A reload starts, then the deadline is moved before it finishes
+        assert_eq!(cache.get(&"key1").await, Some("original".to_string()));
+        assert!(
+            cache
+                .expire_at(&"key1", clock.now() + Duration::from_secs(1))
+                .await
+        );
+        clock.advance(Duration::from_millis(500));
+        gate.notify_one();
+        settle().await;
+
+        assert_eq!(cache.get(&"key1").await, Some("original".to_string()));
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(cache.get(&"key1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_ahead_failure_keeps_old_value() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .refresh_ahead(Duration::from_millis(20), |_key: &'static str| async {
+                Err::<String, _>(crate::LoaderError::from("upstream down"))
+            })
+            .build();
+
+        cache
+            .set(
+                "key1",
+                "original".to_string(),
+                Some(Duration::from_millis(150)),
+            )
+            .await;
+        clock.advance(Duration::from_millis(50));
+
+        assert_eq!(cache.get(&"key1").await, Some("original".to_string()));
+        settle().await;
+        assert_eq!(cache.get(&"key1").await, Some("original".to_string()));
+
+        // The old value still expires at its hard deadline
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(cache.get(&"key1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_with_serves_stale_while_revalidating() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+        let policy = StalePolicy::new(Duration::from_millis(50), Duration::from_secs(5));
+        let version = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let loader = move |_key: &'static str| {
+            let n = version.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move { Ok::<_, LoaderError>(n) }
+        };
+
+        let loaded = cache
+            .get_with("key1", policy, loader.clone())
+            .await
+            .unwrap();
+        assert_eq!(loaded, Lookup::Loaded(0));
+        let fresh = cache
+            .get_with("key1", policy, loader.clone())
+            .await
+            .unwrap();
+        assert_eq!(fresh, Lookup::Fresh(0));
+
+        clock.advance(Duration::from_millis(80));
+        let stale = cache
+            .get_with("key1", policy, loader.clone())
+            .await
+            .unwrap();
+        assert_eq!(stale, Lookup::Stale(0));
+        settle().await;
+
+        let revalidated = cache.get_with("key1", policy, loader).await.unwrap();
+        assert_eq!(revalidated, Lookup::Fresh(1));
+    }
+
+    #[tokio::test]
+    async fn test_get_with_loads_each_key_once() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        let policy = StalePolicy::new(Duration::from_secs(60), Duration::ZERO);
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let lookups: Vec<_> = (0..8)
+            .map(|_| {
+                let (cache, calls) = (cache.clone(), calls.clone());
+                tokio::spawn(async move {
+                    cache
+                        .get_with("key1", policy, move |_key| {
+                            let calls = calls.clone();
+                            async move {
+                                calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                                tokio::task::yield_now().await;
+                                Ok::<_, LoaderError>(7)
+                            }
+                        })
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        let mut loaded = 0;
+        for lookup in lookups {
+            match lookup.await.unwrap() {
+                Lookup::Loaded(7) => loaded += 1,
+                other => assert_eq!(other, Lookup::Fresh(7)),
+            }
+        }
+
+        assert_eq!(loaded, 1);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_with_serves_stale_if_error() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+        let policy = StalePolicy::new(Duration::from_millis(20), Duration::from_millis(100));
+        let failing = |_key: &'static str| async { Err::<&str, _>(LoaderError::from("down")) };
+
+        cache
+            .get_with("key1", policy, |_key| async {
+                Ok::<_, LoaderError>("value1")
+            })
+            .await
+            .unwrap();
+        clock.advance(Duration::from_millis(40));
+
+        for _ in 0..3 {
+            let lookup = cache.get_with("key1", policy, failing).await.unwrap();
+            assert_eq!(lookup, Lookup::Stale("value1"));
+            settle().await;
+            clock.advance(Duration::from_millis(10));
+        }
+
+        // Once the stale window ends the loader error surfaces
+        clock.advance(Duration::from_millis(100));
+        assert!(cache.get_with("key1", policy, failing).await.is_err());
+        assert_eq!(cache.get(&"key1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_early_expiration_weighs_recompute_cost() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .early_expiration(1e9)
+            .build();
+        let policy = StalePolicy::new(Duration::from_secs(60), Duration::ZERO);
+
+        // An expensive value is expired early, but stays cached for other readers
+        cache
+            .get_with("slow", policy, move |_key| {
+                clock.advance(Duration::from_millis(20));
+                async { Ok::<_, LoaderError>("value") }
+            })
+            .await
+            .unwrap();
+        assert_eq!(cache.get(&"slow").await, None);
+        assert_eq!(cache.len().await, 1);
+
+        // Values without a recorded cost only expire at their deadline
+        cache
+            .set("plain", "value", Some(Duration::from_secs(60)))
+            .await;
+        assert_eq!(cache.get(&"plain").await, Some("value"));
+
+        // ...unless the caller reports the cost itself
+        cache
+            .set_with_cost(
+                "costly",
+                "value",
+                Some(Duration::from_secs(60)),
+                Duration::from_millis(20),
+            )
+            .await;
+        assert_eq!(cache.get(&"costly").await, None);
+        assert_eq!(cache.len().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_early_expiration_is_opt_in() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+        let policy = StalePolicy::new(Duration::from_secs(60), Duration::ZERO);
+
+        cache
+            .get_with("slow", policy, move |_key| {
+                clock.advance(Duration::from_millis(20));
+                async { Ok::<_, LoaderError>("value") }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(cache.get(&"slow").await, Some("value"));
+    }
+
+    #[tokio::test]
+    async fn test_ttl_jitter_stays_within_range() {
+        let cache: MiniCache<u32, u32> = MiniCache::builder(Duration::from_secs(1))
+            .ttl_jitter(Jitter::Percent(10.0))
+            .build();
+        let ttl = Duration::from_secs(100);
+
+        let spread: Vec<Duration> = (0..100).map(|_| cache.jittered(ttl)).collect();
+
+        assert!(
+            spread
+                .iter()
+                .all(|d| *d >= Duration::from_secs(90) && *d <= Duration::from_secs(110))
+        );
+        assert!(spread.iter().any(|d| *d != spread[0]));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::time::Duration;
-    use tokio::time::sleep;
+    #[tokio::test(start_paused = true)]
+    async fn test_removed_namespace_is_released() {
+        let cache = MiniCache::<u32, u32>::new(Duration::from_secs(1));
+        let namespace = cache.namespace("temp");
+        namespace.set(1, 1, None).await;
+        let storage = Arc::downgrade(&namespace.inner);
+
+        assert!(cache.remove_namespace("temp"));
+        assert!(!cache.remove_namespace("temp"));
+        assert!(cache.namespace("temp").is_empty().await);
+        assert_eq!(namespace.get(&1).await, Some(1));
+
+        drop(namespace);
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(storage.upgrade().is_none());
+    }
 
     #[tokio::test]
-    async fn test_basic_set_and_get() {
-        let cache = MiniCache::new(Duration::from_secs(1));
+    async fn test_ttl_jitter_percent_is_clamped() {
+        let build = |percent| {
+            MiniCache::<u32, u32>::builder(Duration::from_secs(1))
+                .ttl_jitter(Jitter::Percent(percent))
+                .build()
+        };
+        let ttl = Duration::from_secs(100);
 
-        cache.set("key1", "value1", None).await;
-        let result = cache.get(&"key1").await;
+        let unchanged = build(f64::NAN);
+        assert!((0..100).all(|_| unchanged.jittered(ttl) == ttl));
 
-        assert_eq!(result, Some("value1"));
+        let wide = build(500.0);
+        assert!((0..100).all(|_| {
+            let jittered = wide.jittered(ttl);
+            jittered >= Duration::from_secs(1) && jittered <= Duration::from_secs(199)
+        }));
     }
 
     #[tokio::test]
-    async fn test_get_nonexistent_key() {
-        let cache: MiniCache<&str, &str> = MiniCache::new(Duration::from_secs(1));
-        let result = cache.get(&"nonexistent").await;
+    async fn test_ttl_jitter_is_deterministic_with_seed() {
+        let build = || {
+            MiniCache::<u32, u32>::builder(Duration::from_secs(1))
+                .ttl_jitter(Jitter::Absolute(Duration::from_secs(5)))
+                .seed(42)
+                .build()
+        };
+        let (a, b) = (build(), build());
+        let ttl = Duration::from_secs(3);
 
-        assert_eq!(result, None);
+        for _ in 0..10 {
+            let jittered = a.jittered(ttl);
+            assert_eq!(jittered, b.jittered(ttl));
+            assert!(jittered <= Duration::from_secs(8));
+        }
     }
 
     #[tokio::test]
-    async fn test_ttl_expiration() {
-        let cache = MiniCache::new(Duration::from_millis(100));
+    async fn test_ttl_reports_remaining_time() {
+        let cache = MiniCache::new(Duration::from_secs(1));
 
-        // Set with 50ms TTL
+        cache.set("permanent", "value", None).await;
         cache
-            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .set("temporary", "value", Some(Duration::from_secs(10)))
             .await;
 
-        // Should exist immediately
-        assert_eq!(cache.get(&"key1").await, Some("value1"));
-
-        // Wait for expiration
-        sleep(Duration::from_millis(100)).await;
-
-        // Should be expired
-        assert_eq!(cache.get(&"key1").await, None);
+        assert_eq!(cache.ttl(&"missing").await, None);
+        assert_eq!(cache.ttl(&"permanent").await, Some(None));
+        let remaining = cache.ttl(&"temporary").await.flatten().unwrap();
+        assert!(remaining > Duration::from_secs(9) && remaining <= Duration::from_secs(10));
     }
 
     #[tokio::test]
-    async fn test_no_ttl_persistence() {
-        let cache = MiniCache::new(Duration::from_millis(100));
+    async fn test_expire_and_persist() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+
+        assert!(!cache.expire(&"key1", Duration::from_millis(50)).await);
 
         cache.set("key1", "value1", None).await;
+        cache.set("key2", "value2", None).await;
+        assert!(cache.expire(&"key1", Duration::from_millis(50)).await);
+        assert!(cache.expire(&"key2", Duration::from_millis(50)).await);
+        assert!(cache.persist(&"key2").await);
 
-        // Wait longer than cleanup interval
-        sleep(Duration::from_millis(200)).await;
+        clock.advance(Duration::from_millis(100));
 
-        // Should still exist (no TTL)
-        assert_eq!(cache.get(&"key1").await, Some("value1"));
+        assert_eq!(cache.get(&"key1").await, None);
+        assert_eq!(cache.get(&"key2").await, Some("value2"));
+        assert!(!cache.persist(&"key1").await);
     }
 
     #[tokio::test]
-    async fn test_overwrite_value() {
+    async fn test_expire_at_in_the_past_expires_immediately() {
         let cache = MiniCache::new(Duration::from_secs(1));
 
         cache.set("key1", "value1", None).await;
-        cache.set("key1", "value2", None).await;
+        assert!(cache.expire_at(&"key1", Instant::now()).await);
 
-        assert_eq!(cache.get(&"key1").await, Some("value2"));
+        assert_eq!(cache.get(&"key1").await, None);
     }
 
     #[tokio::test]
-    async fn test_remove() {
-        let cache = MiniCache::new(Duration::from_secs(1));
+    async fn test_touch_resets_original_ttl() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
 
-        cache.set("key1", "value1", None).await;
+        cache
+            .set("key1", "value1", Some(Duration::from_millis(100)))
+            .await;
+        clock.advance(Duration::from_millis(60));
+        assert!(cache.touch(&"key1").await);
+        clock.advance(Duration::from_millis(60));
+
+        // Without the touch the entry would have expired by now
         assert_eq!(cache.get(&"key1").await, Some("value1"));
+        assert!(!cache.touch(&"missing").await);
+    }
 
-        cache.remove(&"key1").await;
-        assert_eq!(cache.get(&"key1").await, None);
+    /// Returns how many keys are tagged, checking both directions of the index agree.
+    async fn tagged_keys(cache: &MiniCache<&'static str, &'static str>) -> usize {
+        let storage = cache.inner.read().await;
+        let by_tag: usize = storage.tags.values().map(HashSet::len).sum();
+        let by_key: usize = storage.tagged.values().map(Vec::len).sum();
+        assert_eq!(by_tag, by_key);
+        storage.tagged.len()
     }
 
     #[tokio::test]
-    async fn test_clear() {
+    async fn test_invalidate_tag() {
         let cache = MiniCache::new(Duration::from_secs(1));
 
-        cache.set("key1", "value1", None).await;
-        cache.set("key2", "value2", None).await;
-
-        assert_eq!(cache.len().await, 2);
-
-        cache.clear().await;
+        cache.set_tagged("key1", "value1", None, ["a"]).await;
+        cache.set_tagged("key2", "value2", None, ["a", "b"]).await;
+        cache.set_tagged("key3", "value3", None, ["b"]).await;
+        cache.set("key4", "value4", None).await;
 
-        assert_eq!(cache.len().await, 0);
+        assert_eq!(cache.invalidate_tag("a").await, 2);
         assert_eq!(cache.get(&"key1").await, None);
         assert_eq!(cache.get(&"key2").await, None);
+        assert_eq!(cache.get(&"key3").await, Some("value3"));
+        assert_eq!(cache.get(&"key4").await, Some("value4"));
+
+        assert_eq!(cache.invalidate_tag("a").await, 0);
+        assert_eq!(cache.invalidate_tag("b").await, 1);
+        assert_eq!(tagged_keys(&cache).await, 0);
     }
 
     #[tokio::test]
-    async fn test_contains() {
+    async fn test_tags_follow_overwrite_and_remove() {
         let cache = MiniCache::new(Duration::from_secs(1));
 
-        assert!(!cache.contains(&"key1").await);
+        cache.set_tagged("key1", "value1", None, ["a"]).await;
+        cache.set("key1", "value2", None).await;
+        assert_eq!(cache.invalidate_tag("a").await, 0);
+        assert_eq!(cache.get(&"key1").await, Some("value2"));
 
-        cache.set("key1", "value1", None).await;
-        assert!(cache.contains(&"key1").await);
+        cache.set_tagged("key2", "value2", None, ["a"]).await;
+        cache.remove(&"key2").await;
+        assert_eq!(tagged_keys(&cache).await, 0);
 
-        cache.remove(&"key1").await;
-        assert!(!cache.contains(&"key1").await);
+        cache.set_tagged("key3", "value3", None, ["a"]).await;
+        cache.clear().await;
+        assert_eq!(tagged_keys(&cache).await, 0);
     }
 
     #[tokio::test]
-    async fn test_contains_with_expired_key() {
-        let cache = MiniCache::new(Duration::from_millis(100));
+    async fn test_tags_follow_expiry_and_eviction() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .capacity(2)
+            .build();
 
         cache
-            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .set_tagged("key1", "value1", Some(Duration::from_secs(5)), ["a"])
             .await;
-        assert!(cache.contains(&"key1").await);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(cache.get(&"key1").await, None);
+        assert_eq!(tagged_keys(&cache).await, 0);
 
-        sleep(Duration::from_millis(100)).await;
-        assert!(!cache.contains(&"key1").await);
+        cache.set_tagged("key2", "value2", None, ["a"]).await;
+        cache.set("key3", "value3", None).await;
+        cache.set("key4", "value4", None).await;
+        assert_eq!(tagged_keys(&cache).await, 0);
+        assert_eq!(cache.invalidate_tag("a").await, 0);
     }
 
-    #[tokio::test]
-    async fn test_len() {
-        let cache = MiniCache::new(Duration::from_secs(1));
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_forgets_tags_of_expired_entries() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_millis(20))
+            .clock(clock.clone())
+            .build();
 
-        assert_eq!(cache.len().await, 0);
+        cache
+            .set_tagged("key1", "value1", Some(Duration::from_millis(10)), ["a"])
+            .await;
+        clock.advance(Duration::from_millis(60));
+        sleep(Duration::from_millis(60)).await;
 
-        cache.set("key1", "value1", None).await;
-        assert_eq!(cache.len().await, 1);
+        assert_eq!(tagged_keys(&cache).await, 0);
+    }
 
-        cache.set("key2", "value2", None).await;
-        assert_eq!(cache.len().await, 2);
+    #[tokio::test]
+    async fn test_invalidate_tag_reaches_disk_tier() {
+        let dir = crate::disk::TempDir::new("core-tags");
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .capacity(1)
+            .disk_tier(crate::DiskTier::open(dir.path()).unwrap())
+            .build();
 
-        cache.remove(&"key1").await;
+        cache.set_tagged(1, "value1".to_string(), None, ["a"]).await;
+        cache.set(2, "value2".to_string(), None).await;
+
+        // Key 1 now lives on disk and keeps its tag
+        assert_eq!(cache.invalidate_tag("a").await, 1);
+        assert_eq!(cache.get(&1).await, None);
         assert_eq!(cache.len().await, 1);
     }
 
     #[tokio::test]
-    async fn test_len_with_expired_items() {
-        let cache = MiniCache::new(Duration::from_millis(100));
+    async fn test_scan_prefix() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
 
+        cache.set("user:1", "alice", None).await;
         cache
-            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .set("user:2", "bob", Some(Duration::from_secs(5)))
             .await;
-        cache.set("key2", "value2", None).await;
+        cache.set("order:1", "lamp", None).await;
 
-        assert_eq!(cache.len().await, 2);
+        let mut keys = cache.scan_prefix("user:").await;
+        keys.sort();
+        assert_eq!(keys, vec!["user:1", "user:2"]);
 
-        sleep(Duration::from_millis(100)).await;
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(cache.scan_prefix("user:").await, vec!["user:1"]);
+        assert!(cache.scan_prefix("product:").await.is_empty());
+    }
 
-        // Only key2 should remain (key1 expired)
-        assert_eq!(cache.len().await, 1);
+    #[tokio::test]
+    async fn test_scan_prefix_seeks_ordered_index() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .ordered()
+            .build();
+
+        for key in ["user:3", "order:1", "user:1", "users", "user:2", "vip:1"] {
+            cache.set(key, "value", None).await;
+        }
+        cache
+            .set("user:0", "value", Some(Duration::from_secs(5)))
+            .await;
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!(
+            cache.scan_prefix("user:").await,
+            vec!["user:1", "user:2", "user:3"]
+        );
+        assert_eq!(cache.scan_prefix("user").await.len(), 4);
+        assert!(cache.scan_prefix("zzz").await.is_empty());
     }
 
     #[tokio::test]
-    async fn test_keys() {
-        let cache = MiniCache::new(Duration::from_secs(1));
+    async fn test_keys_matching_and_remove_matching() {
+        let store = crate::MemoryStore::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
 
-        cache.set("key1", "value1", None).await;
-        cache.set("key2", "value2", None).await;
-        cache.set("key3", "value3", None).await;
+        cache.set("user:1:name".to_string(), "alice", None).await;
+        cache.set("user:2:name".to_string(), "bob", None).await;
+        cache.set("user:1:email".to_string(), "a@x", None).await;
 
-        let mut keys = cache.keys().await;
+        let mut keys = cache.keys_matching("user:?:name").await;
         keys.sort();
+        assert_eq!(keys, vec!["user:1:name", "user:2:name"]);
 
-        assert_eq!(keys, vec!["key1", "key2", "key3"]);
+        assert_eq!(cache.remove_matching("user:*:name").await, 2);
+        assert_eq!(cache.keys().await, vec!["user:1:email"]);
+        assert_eq!(store.len(), 1);
+        assert_eq!(cache.remove_matching("nothing*").await, 0);
     }
 
     #[tokio::test]
-    async fn test_keys_with_expired_items() {
-        let cache = MiniCache::new(Duration::from_millis(100));
+    async fn test_keys_matching_includes_disk_tier() {
+        let dir = crate::disk::TempDir::new("core-glob");
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .capacity(1)
+            .disk_tier(crate::DiskTier::open(dir.path()).unwrap())
+            .build();
 
         cache
-            .set("key1", "value1", Some(Duration::from_millis(50)))
+            .set("a:1".to_string(), "value1".to_string(), None)
+            .await;
+        cache
+            .set("a:2".to_string(), "value2".to_string(), None)
             .await;
-        cache.set("key2", "value2", None).await;
-        cache.set("key3", "value3", None).await;
 
-        sleep(Duration::from_millis(100)).await;
+        let mut keys = cache.keys_matching("a:*").await;
+        keys.sort();
+        assert_eq!(keys, vec!["a:1", "a:2"]);
+        assert_eq!(cache.remove_matching("a:*").await, 2);
+        assert!(cache.is_empty().await);
+    }
 
-        let mut keys = cache.keys().await;
+    async fn scan_all(cache: &MiniCache<u32, u32>, count: usize) -> Vec<(u32, u32)> {
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = cache.scan(cursor, count).await;
+            assert!(batch.len() <= count);
+            seen.extend(batch);
+            if next == 0 {
+                return seen;
+            }
+            cursor = next;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_visits_every_entry_once() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        for i in 0..100 {
+            cache.set(i, i * 2, None).await;
+        }
+
+        let mut seen = scan_all(&cache, 7).await;
+        seen.sort();
+        assert_eq!(seen, (0..100).map(|i| (i, i * 2)).collect::<Vec<_>>());
+
+        let empty: MiniCache<u32, u32> = MiniCache::new(Duration::from_secs(1));
+        assert_eq!(empty.scan(0, 10).await, (0, Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn test_scan_survives_concurrent_changes() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        for i in 0..50 {
+            cache.set(i, i, None).await;
+        }
+
+        let (mut cursor, mut seen) = cache.scan(0, 10).await;
+        // Remove some keys, overwrite others and add new ones mid-scan
+        for i in 40..50 {
+            cache.remove(&i).await;
+        }
+        for i in 0..40 {
+            cache.set(i, i + 1000, None).await;
+        }
+        for i in 100..200 {
+            cache.set(i, i, None).await;
+        }
+        while cursor != 0 {
+            let (next, batch) = cache.scan(cursor, 10).await;
+            seen.extend(batch);
+            cursor = next;
+        }
+
+        let mut keys: Vec<u32> = seen.iter().map(|(k, _)| *k).collect();
         keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), seen.len());
+        for i in 0..40 {
+            assert!(keys.contains(&i));
+        }
+    }
 
-        // Only key2 and key3 should remain (key1 expired)
-        assert_eq!(keys, vec!["key2", "key3"]);
+    #[tokio::test]
+    async fn test_scan_skips_expired_entries() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+
+        cache.set(1, 1, Some(Duration::from_secs(5))).await;
+        cache.set(2, 2, None).await;
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!(scan_all(&cache, 10).await, vec![(2, 2)]);
     }
 
     #[tokio::test]
-    async fn test_automatic_cleanup() {
-        let cache = MiniCache::new(Duration::from_millis(50));
+    async fn test_range_queries() {
+        for cache in [
+            MiniCache::builder(Duration::from_secs(1)).ordered().build(),
+            MiniCache::new(Duration::from_secs(1)),
+        ] {
+            for i in [5, 1, 9, 3, 7] {
+                cache.set(i, i * 10, None).await;
+            }
+
+            assert_eq!(cache.range(3..7).await, vec![(3, 30), (5, 50)]);
+            assert_eq!(cache.range(3..=7).await, vec![(3, 30), (5, 50), (7, 70)]);
+            assert_eq!(cache.range(..3).await, vec![(1, 10)]);
+            assert_eq!(cache.range(8..).await, vec![(9, 90)]);
+            assert_eq!(cache.range(..).await.len(), 5);
+            let (lo, hi) = (7, 3);
+            assert!(cache.range(lo..hi).await.is_empty());
+            assert!(
+                cache
+                    .range((Bound::Excluded(5), Bound::Excluded(5)))
+                    .await
+                    .is_empty()
+            );
+            assert_eq!(cache.first().await, Some((1, 10)));
+            assert_eq!(cache.last().await, Some((9, 90)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_skips_expired_entries() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .ordered()
+            .build();
+
+        cache.set(1, "a", Some(Duration::from_secs(5))).await;
+        cache.set(2, "b", None).await;
+        cache.set(3, "c", Some(Duration::from_secs(5))).await;
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!(cache.range(..).await, vec![(2, "b")]);
+        assert_eq!(cache.first().await, Some((2, "b")));
+        assert_eq!(cache.last().await, Some((2, "b")));
+        assert_eq!(cache.pop_first().await, Some((2, "b")));
+        assert_eq!(cache.pop_first().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_pop_first_and_last() {
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .ordered()
+            .capacity(10)
+            .build();
+        for i in 1..=4 {
+            cache.set(i, i, None).await;
+        }
+
+        assert_eq!(cache.pop_first().await, Some((1, 1)));
+        assert_eq!(cache.pop_last().await, Some((4, 4)));
+        assert_eq!(cache.range(..).await, vec![(2, 2), (3, 3)]);
+
+        cache.remove(&2).await;
+        cache.set(0, 0, None).await;
+        assert_eq!(cache.first().await, Some((0, 0)));
+
+        cache.clear().await;
+        assert_eq!(cache.first().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_ordered_index_follows_eviction() {
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .ordered()
+            .capacity(2)
+            .build();
+
+        cache.set(1, 1, None).await;
+        cache.set(2, 2, None).await;
+        cache.set(3, 3, None).await;
+
+        assert_eq!(cache.range(..).await, vec![(2, 2), (3, 3)]);
+        assert_eq!(
+            cache
+                .inner
+                .read()
+                .await
+                .index
+                .as_ref()
+                .map(|i| i.range(Bound::Unbounded, Bound::Unbounded).count()),
+            Some(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_incr_and_decr() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(cache.incr(&"hits", 1, None).await, 1);
+        assert_eq!(cache.incr(&"hits", 5, None).await, 6);
+        assert_eq!(cache.decr(&"hits", 10, None).await, -4);
+        assert_eq!(cache.incr(&"max", i64::MAX, None).await, i64::MAX);
+        assert_eq!(cache.incr(&"max", 1, None).await, i64::MAX);
+        assert_eq!(cache.get(&"hits").await, Some(-4));
+    }
+
+    #[tokio::test]
+    async fn test_incr_is_atomic() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    for _ in 0..50 {
+                        cache.incr(&"hits", 1, None).await;
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(cache.get(&"hits").await, Some(1000));
+    }
+
+    #[tokio::test]
+    async fn test_incr_ttl_handling() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+        let window = Some(Duration::from_secs(10));
 
+        cache.incr(&"fixed", 1, window).await;
+        cache.incr(&"sliding", 1, window).await;
+        clock.advance(Duration::from_secs(6));
+        // The fixed window keeps its deadline, the sliding one restarts it
+        cache.incr(&"fixed", 1, Some(Duration::from_secs(60))).await;
         cache
-            .set("key1", "value1", Some(Duration::from_millis(25)))
+            .incr_and_expire(&"sliding", 1, Duration::from_secs(10))
             .await;
-        cache.set("key2", "value2", None).await;
+        clock.advance(Duration::from_secs(4));
 
-        // Wait for cleanup to run
-        sleep(Duration::from_millis(100)).await;
+        assert_eq!(cache.get(&"fixed").await, None);
+        assert_eq!(cache.get(&"sliding").await, Some(2));
 
-        // Expired item should be cleaned up
-        assert_eq!(cache.get(&"key1").await, None);
-        assert_eq!(cache.get(&"key2").await, Some("value2"));
+        // A counter that expired starts over
+        assert_eq!(cache.incr(&"fixed", 1, window).await, 1);
     }
 
     #[tokio::test]
-    async fn test_different_key_types() {
-        let cache: MiniCache<i32, String> = MiniCache::new(Duration::from_secs(1));
+    async fn test_get_and_reset() {
+        let clock = MockClock::new();
+        let cache: MiniCache<&str, i64> = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
 
-        cache.set(1, "value1".to_string(), None).await;
-        cache.set(2, "value2".to_string(), None).await;
+        assert_eq!(cache.get_and_reset(&"hits").await, None);
+        assert!(!cache.contains(&"hits").await);
 
-        assert_eq!(cache.get(&1).await, Some("value1".to_string()));
-        assert_eq!(cache.get(&2).await, Some("value2".to_string()));
-        assert_eq!(cache.get(&3).await, None);
+        cache.incr(&"hits", 7, Some(Duration::from_secs(10))).await;
+        assert_eq!(cache.get_and_reset(&"hits").await, Some(7));
+        assert_eq!(cache.get(&"hits").await, Some(0));
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.get(&"hits").await, None);
     }
 
     #[tokio::test]
-    async fn test_concurrent_access() {
-        let cache = Arc::new(MiniCache::new(Duration::from_secs(1)));
-        let mut handles = vec![];
+    async fn test_incr_float() {
+        let cache = MiniCache::new(Duration::from_secs(1));
 
-        // Spawn multiple tasks that write to cache
-        for i in 0..10 {
-            let cache_clone = cache.clone();
-            let handle = tokio::spawn(async move {
-                cache_clone.set(i, format!("value{}", i), None).await;
-            });
-            handles.push(handle);
-        }
+        assert_eq!(cache.incr_float(&"total", 1.5, None).await, 1.5);
+        assert_eq!(cache.incr_float(&"total", -0.25, None).await, 1.25);
+        assert_eq!(cache.get_and_reset(&"total").await, Some(1.25));
+        assert_eq!(cache.get(&"total").await, Some(0.0));
+    }
 
-        // Wait for all writes to complete
-        for handle in handles {
-            handle.await.unwrap();
+    #[tokio::test]
+    async fn test_wait_for_wakes_only_the_written_key() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        cache.set("ready", 0, None).await;
+        assert_eq!(cache.wait_for(&"ready", Duration::ZERO).await, Some(0));
+
+        let waiter = cache.clone();
+        let first =
+            tokio::spawn(async move { waiter.wait_for(&"a", Duration::from_secs(5)).await });
+        let waiter = cache.clone();
+        let second =
+            tokio::spawn(async move { waiter.wait_for(&"b", Duration::from_secs(5)).await });
+        while cache.inner.read().await.waiters.len() < 2 {
+            tokio::task::yield_now().await;
         }
 
-        // Verify all values are present
-        for i in 0..10 {
-            assert_eq!(cache.get(&i).await, Some(format!("value{}", i)));
+        cache.set("a", 1, None).await;
+        assert_eq!(first.await.unwrap(), Some(1));
+        assert!(!second.is_finished());
+        assert!(cache.inner.read().await.waiters.contains_key(&"b"));
+
+        cache.incr(&"b", 2, None).await;
+        assert_eq!(second.await.unwrap(), Some(2));
+        assert!(cache.inner.read().await.waiters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_times_out() {
+        let cache: MiniCache<&str, i32> = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(
+            cache.wait_for(&"never", Duration::from_millis(10)).await,
+            None
+        );
+        assert!(cache.inner.read().await.waiters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conditional_sets_treat_expired_as_absent() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+
+        assert!(!cache.set_if_present("key", 1, None).await);
+        assert!(!cache.contains(&"key").await);
+        assert!(
+            cache
+                .set_if_absent("key", 1, Some(Duration::from_secs(10)))
+                .await
+        );
+        assert!(!cache.set_if_absent("key", 2, None).await);
+        assert_eq!(cache.get(&"key").await, Some(1));
+
+        assert!(
+            cache
+                .set_if_present("key", 3, Some(Duration::from_secs(5)))
+                .await
+        );
+        assert_eq!(cache.ttl(&"key").await, Some(Some(Duration::from_secs(5))));
+
+        clock.advance(Duration::from_secs(5));
+        assert!(!cache.set_if_present("key", 4, None).await);
+        assert!(cache.set_if_absent("key", 5, None).await);
+        assert_eq!(cache.get(&"key").await, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_set_if_absent_has_one_winner() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        let attempts: Vec<_> = (0..16)
+            .map(|i| {
+                let cache = cache.clone();
+                tokio::spawn(async move { cache.set_if_absent("job", i, None).await })
+            })
+            .collect();
+
+        let mut winners = 0;
+        for attempt in attempts {
+            winners += usize::from(attempt.await.unwrap());
         }
+        assert_eq!(winners, 1);
     }
 
     #[tokio::test]
-    async fn test_cache_clone() {
-        let cache1 = MiniCache::new(Duration::from_secs(1));
-        let cache2 = cache1.clone();
+    async fn test_replace_keeps_ttl_and_tags() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+        cache
+            .set_tagged("key", "old", Some(Duration::from_secs(10)), ["group"])
+            .await;
 
-        cache1.set("key1", "value1", None).await;
+        assert_eq!(cache.replace(&"key", "new").await, Some("old"));
+        assert_eq!(cache.replace(&"missing", "new").await, None);
+        assert_eq!(cache.get(&"missing").await, Some("new"));
+        assert_eq!(cache.ttl(&"missing").await, Some(None));
 
-        // Both caches should see the same data (shared inner state)
-        assert_eq!(cache2.get(&"key1").await, Some("value1"));
+        assert_eq!(cache.invalidate_tag("group").await, 1);
+        cache
+            .set("key", "again", Some(Duration::from_secs(10)))
+            .await;
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.replace(&"key", "late").await, None);
+        assert_eq!(cache.ttl(&"key").await, Some(None));
+    }
 
-        cache2.set("key2", "value2", None).await;
-        assert_eq!(cache1.get(&"key2").await, Some("value2"));
+    #[tokio::test]
+    async fn test_get_and_remove() {
+        let store = crate::MemoryStore::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .write_through(store.clone())
+            .build();
+        cache.set("key", "value", None).await;
+
+        assert_eq!(cache.get_and_remove(&"key").await, Some("value"));
+        assert_eq!(cache.get_and_remove(&"key").await, None);
+        assert_eq!(cache.get(&"key").await, None);
     }
 }
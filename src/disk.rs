@@ -0,0 +1,1058 @@
+//! Disk-backed second tier for `MiniCache`: append-only segment files plus an in-memory index.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::hash::Hash;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Default maximum size of a single segment file (64 MiB).
+const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// File extension used for segment files.
+const SEGMENT_EXTENSION: &str = "seg";
+
+/// Conversion between a value and the bytes stored in a segment file.
+///
+/// Implemented for `String`, `Vec<u8>` and the primitive integer types. Implement it for
+/// your own value types to use them with a [`DiskTier`].
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::Codec;
+///
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// impl Codec for Point {
+///     fn encode(&self) -> Vec<u8> {
+///         let mut bytes = self.x.to_le_bytes().to_vec();
+///         bytes.extend_from_slice(&self.y.to_le_bytes());
+///         bytes
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Option<Self> {
+///         let x = i32::from_le_bytes(bytes.get(0..4)?.try_into().ok()?);
+///         let y = i32::from_le_bytes(bytes.get(4..8)?.try_into().ok()?);
+///         Some(Point { x, y })
+///     }
+/// }
+/// ```
+pub trait Codec: Sized {
+    /// Serializes the value into bytes.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Deserializes a value previously produced by [`Codec::encode`].
+    ///
+    /// Returns `None` if the bytes are not a valid encoding.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl Codec for Vec<u8> {
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl Codec for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+macro_rules! impl_codec_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                fn encode(&self) -> Vec<u8> {
+                    self.to_le_bytes().to_vec()
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// A closed segment is compacted once less than this fraction of its bytes is live.
+const COMPACTION_RATIO: f64 = 0.5;
+
+/// How long the writer waits before retrying writes that failed.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Location of a value inside a segment file.
+///
+/// `token` identifies this copy of the value; it is kept when the value moves from the
+/// write queue to a segment or from one segment to another during compaction.
+#[derive(Clone, Copy)]
+struct Slot {
+    segment: u64,
+    offset: u64,
+    len: usize,
+    expire_at: Option<Instant>,
+    ttl: Option<Duration>,
+    token: u64,
+}
+
+/// An evicted value waiting for the writer thread. It stays readable from memory until
+/// it has been written.
+struct Staged {
+    bytes: Arc<[u8]>,
+    expire_at: Option<Instant>,
+    ttl: Option<Duration>,
+    token: u64,
+}
+
+/// Size of a segment file and how much of it the index still points into.
+#[derive(Default)]
+struct Segment {
+    size: u64,
+    live: usize,
+    live_bytes: u64,
+}
+
+/// A value handed to the writer, either freshly spilled or moved out of a segment that
+/// is being compacted. It is only indexed if the key still holds the copy `token`.
+struct Record<K> {
+    key: K,
+    bytes: Arc<[u8]>,
+    expire_at: Option<Instant>,
+    ttl: Option<Duration>,
+    token: u64,
+    staged: bool,
+}
+
+/// The segment the writer thread appends to.
+struct Active {
+    id: u64,
+    file: File,
+    size: u64,
+}
+
+/// An entry read back from a [`DiskTier`], with the token of the copy that was read.
+pub(crate) struct Spilled<T> {
+    pub(crate) value: T,
+    pub(crate) expire_at: Option<Instant>,
+    pub(crate) ttl: Option<Duration>,
+    pub(crate) token: u64,
+}
+
+/// Mutable state of a `DiskTier`, guarded by a single mutex that is never held during
+/// file I/O.
+struct DiskState<K> {
+    index: HashMap<K, Slot>,
+    staged: HashMap<K, Staged>,
+    segments: BTreeMap<u64, Segment>,
+    active: Option<u64>,
+    next_segment: u64,
+    next_token: u64,
+    segment_size: u64,
+    /// Closed segments below the compaction ratio, waiting to be rewritten.
+    victims: BTreeSet<u64>,
+    /// Segments nothing points into any more, waiting for their files to be deleted.
+    doomed: Vec<u64>,
+    /// Number of flushes requested, and the last one the writer has completed.
+    requested: u64,
+    served: u64,
+    retry_at: Option<Instant>,
+    error: Option<io::Error>,
+    shutdown: bool,
+}
+
+impl<K> DiskState<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new() -> Self {
+        DiskState {
+            index: HashMap::new(),
+            staged: HashMap::new(),
+            segments: BTreeMap::new(),
+            active: None,
+            next_segment: 0,
+            next_token: 0,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            victims: BTreeSet::new(),
+            doomed: Vec::new(),
+            requested: 0,
+            served: 0,
+            retry_at: None,
+            error: None,
+            shutdown: false,
+        }
+    }
+
+    fn has_work(&self, now: Instant) -> bool {
+        self.shutdown
+            || self.requested > self.served
+            || !self.doomed.is_empty()
+            || !self.victims.is_empty()
+            || (!self.staged.is_empty() && self.retry_at.is_none_or(|t| now >= t))
+    }
+
+    /// Drops a reference into the segment of `slot`.
+    fn release(&mut self, slot: &Slot) {
+        if let Some(segment) = self.segments.get_mut(&slot.segment) {
+            segment.live -= 1;
+            segment.live_bytes -= slot.len as u64;
+        }
+        if self.active != Some(slot.segment) {
+            self.reclaim(slot.segment);
+        }
+    }
+
+    /// Queues a closed segment for deletion once nothing points into it, or for
+    /// compaction once less than [`COMPACTION_RATIO`] of its bytes are live.
+    fn reclaim(&mut self, id: u64) {
+        let Some(segment) = self.segments.get(&id) else {
+            return;
+        };
+        if segment.live == 0 {
+            self.segments.remove(&id);
+            self.doomed.push(id);
+        } else if (segment.live_bytes as f64) < segment.size as f64 * COMPACTION_RATIO {
+            self.victims.insert(id);
+        }
+    }
+
+    /// Copies out every queued value for the writer, leaving it readable meanwhile.
+    fn staged_records(&self) -> Vec<Record<K>> {
+        self.staged
+            .iter()
+            .map(|(key, staged)| Record {
+                key: key.clone(),
+                bytes: staged.bytes.clone(),
+                expire_at: staged.expire_at,
+                ttl: staged.ttl,
+                token: staged.token,
+                staged: true,
+            })
+            .collect()
+    }
+
+    /// Takes the segments queued for compaction and returns their live slots.
+    fn victim_slots(&mut self, now: Instant) -> Vec<(K, Slot)> {
+        let victims = std::mem::take(&mut self.victims);
+        if victims.is_empty() {
+            return Vec::new();
+        }
+        self.index
+            .iter()
+            .filter(|(_, slot)| victims.contains(&slot.segment) && is_live(slot.expire_at, now))
+            .map(|(key, slot)| (key.clone(), *slot))
+            .collect()
+    }
+}
+
+/// State shared between a `DiskTier` and its writer thread.
+struct Shared<K> {
+    dir: PathBuf,
+    state: Mutex<DiskState<K>>,
+    /// Wakes the writer when there is something to do.
+    work: Condvar,
+    /// Wakes `flush` callers when the writer finishes a pass.
+    idle: Condvar,
+}
+
+impl<K> Shared<K> {
+    fn lock(&self) -> MutexGuard<'_, DiskState<K>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn segment_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{id:08}.{SEGMENT_EXTENSION}"))
+    }
+}
+
+impl<K> Shared<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Body of the writer thread: writes queued values, compacts mostly dead segments
+    /// and deletes unreferenced ones, until the tier is dropped.
+    fn run(&self) {
+        let mut active = None;
+        let mut state = self.lock();
+        loop {
+            if state.shutdown {
+                return;
+            }
+            let now = Instant::now();
+            if !state.has_work(now) {
+                state = match state.retry_at {
+                    Some(at) => {
+                        let wait = at.saturating_duration_since(now);
+                        self.work
+                            .wait_timeout(state, wait)
+                            .unwrap_or_else(|e| e.into_inner())
+                            .0
+                    }
+                    None => self.work.wait(state).unwrap_or_else(|e| e.into_inner()),
+                };
+                continue;
+            }
+            let serving = state.requested;
+            let mut records = state.staged_records();
+            let victims = state.victim_slots(now);
+            state.retry_at = None;
+            drop(state);
+
+            records.extend(victims.into_iter().filter_map(|(key, slot)| {
+                let bytes = read_slot(&self.segment_path(slot.segment), &slot).ok()?;
+                Some(Record {
+                    key,
+                    bytes: bytes.into(),
+                    expire_at: slot.expire_at,
+                    ttl: slot.ttl,
+                    token: slot.token,
+                    staged: false,
+                })
+            }));
+            let result = self.append(&mut active, records);
+
+            state = self.lock();
+            match result {
+                Ok(()) => state.error = None,
+                Err(e) => {
+                    state.error = Some(e);
+                    state.retry_at = Some(Instant::now() + RETRY_DELAY);
+                }
+            }
+            let doomed = std::mem::take(&mut state.doomed);
+            drop(state);
+            for id in doomed {
+                let _ = fs::remove_file(self.segment_path(id));
+            }
+
+            state = self.lock();
+            state.served = serving;
+            self.idle.notify_all();
+        }
+    }
+
+    /// Appends `records` to the active segment, starting new segments as they fill up,
+    /// and indexes the ones that are still current.
+    fn append(&self, active: &mut Option<Active>, records: Vec<Record<K>>) -> io::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let segment_size = {
+            let state = self.lock();
+            // `clear` drops every segment, including the one being appended to
+            if active.as_ref().is_some_and(|a| state.active != Some(a.id)) {
+                *active = None;
+            }
+            state.segment_size
+        };
+
+        let mut written = Vec::with_capacity(records.len());
+        let mut closed = Vec::new();
+        let mut result = Ok(());
+        for record in records {
+            let len = record.bytes.len() as u64;
+            if active
+                .as_ref()
+                .is_none_or(|a| a.size > 0 && a.size + len > segment_size)
+            {
+                match self.roll(&mut closed) {
+                    Ok(next) => *active = Some(next),
+                    Err(e) => {
+                        result = Err(e);
+                        break;
+                    }
+                }
+            }
+            let Some(segment) = active.as_mut() else {
+                break;
+            };
+            if let Err(e) = segment.file.write_all(&record.bytes) {
+                // Part of the record may have been written; continue in a new segment
+                *active = None;
+                result = Err(e);
+                break;
+            }
+            written.push((record, segment.id, segment.size));
+            segment.size += len;
+        }
+        self.commit(written, closed);
+        result
+    }
+
+    /// Creates the next segment file and makes it the active one, adding the previous
+    /// one to `closed`.
+    fn roll(&self, closed: &mut Vec<u64>) -> io::Result<Active> {
+        let id = {
+            let mut state = self.lock();
+            state.next_segment += 1;
+            state.next_segment - 1
+        };
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(self.segment_path(id))?;
+        let mut state = self.lock();
+        state.segments.insert(id, Segment::default());
+        closed.extend(state.active.replace(id));
+        Ok(Active { id, file, size: 0 })
+    }
+
+    /// Points the index at the written records whose key still holds the same copy,
+    /// then reclaims the segments closed while writing them.
+    fn commit(&self, written: Vec<(Record<K>, u64, u64)>, closed: Vec<u64>) {
+        let mut guard = self.lock();
+        let state = &mut *guard;
+        for (record, id, offset) in written {
+            let len = record.bytes.len();
+            let Some(segment) = state.segments.get_mut(&id) else {
+                continue;
+            };
+            segment.size += len as u64;
+            let current = if record.staged {
+                state
+                    .staged
+                    .get(&record.key)
+                    .is_some_and(|s| s.token == record.token)
+            } else {
+                state
+                    .index
+                    .get(&record.key)
+                    .is_some_and(|s| s.token == record.token)
+            };
+            if !current {
+                continue;
+            }
+            segment.live += 1;
+            segment.live_bytes += len as u64;
+            if record.staged {
+                state.staged.remove(&record.key);
+            }
+            let slot = Slot {
+                segment: id,
+                offset,
+                len,
+                expire_at: record.expire_at,
+                ttl: record.ttl,
+                token: record.token,
+            };
+            if let Some(old) = state.index.insert(record.key, slot) {
+                state.release(&old);
+            }
+        }
+        for id in closed {
+            state.reclaim(id);
+        }
+    }
+}
+
+/// A local on-disk store used as the second tier of a `MiniCache`.
+///
+/// Values are appended to segment files in a directory, and an in-memory index maps each
+/// key to the segment, offset and expiry of its latest value. All file I/O happens on a
+/// background writer thread or, when an entry is promoted back into memory, on the
+/// blocking thread pool, never while the cache's lock is held. An evicted entry is kept
+/// in memory until the writer has written it, so it stays readable throughout.
+///
+/// When every value in an old segment has been overwritten, removed or expired, the
+/// segment file is deleted. Once less than half of an old segment is still live, its
+/// live values are rewritten to the newest segment so its space can be reclaimed.
+///
+/// The tier is a cache, not a database: nothing survives a restart, and
+/// [`open`](Self::open) deletes any segment files it finds in the directory.
+///
+/// Attach it to a capacity-bounded cache with [`MiniCacheBuilder::disk_tier`]; entries
+/// evicted from memory are then spilled here and promoted back on the next `get`.
+/// Failed writes are retried, and [`MiniCache::flush`] reports them.
+///
+/// [`MiniCacheBuilder::disk_tier`]: crate::MiniCacheBuilder::disk_tier
+/// [`MiniCache::flush`]: crate::MiniCache::flush
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{DiskTier, MiniCache};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() -> std::io::Result<()> {
+///     let dir = std::env::temp_dir().join("minicache-disk-tier-doc");
+///     let cache = MiniCache::builder(Duration::from_secs(60))
+///         .capacity(1)
+///         .disk_tier(DiskTier::open(&dir)?)
+///         .build();
+///
+///     cache.set(1, "first".to_string(), None).await;
+///     cache.set(2, "second".to_string(), None).await; // evicts key 1 to disk
+///
+///     assert_eq!(cache.get(&1).await, Some("first".to_string()));
+///     assert_eq!(cache.len().await, 2);
+/// #   let _ = std::fs::remove_dir_all(&dir);
+///     Ok(())
+/// }
+/// ```
+pub struct DiskTier<K> {
+    shared: Arc<Shared<K>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl<K> DiskTier<K>
+where
+    K: Hash + Eq + Clone + Send + 'static,
+{
+    /// Opens a disk tier in `dir`, creating the directory if needed, and starts its
+    /// writer thread.
+    ///
+    /// **Every `.seg` file already in `dir` is deleted**, since the tier never reuses
+    /// what a previous run left behind. Give each tier a directory of its own.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or cleaned up, or if the
+    /// writer thread cannot be started.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == SEGMENT_EXTENSION) {
+                fs::remove_file(path)?;
+            }
+        }
+        let shared = Arc::new(Shared {
+            dir,
+            state: Mutex::new(DiskState::new()),
+            work: Condvar::new(),
+            idle: Condvar::new(),
+        });
+        let writer = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("minicache-disk-writer".to_string())
+                .spawn(move || shared.run())?
+        };
+        Ok(DiskTier {
+            shared,
+            writer: Some(writer),
+        })
+    }
+
+    /// Sets the size at which the active segment file is closed and a new one started.
+    ///
+    /// Smaller segments let space be reclaimed sooner at the cost of more files.
+    /// Defaults to 64 MiB.
+    pub fn segment_size(self, bytes: u64) -> Self {
+        self.shared.lock().segment_size = bytes.max(1);
+        self
+    }
+
+    /// Queues the encoded value for `key`, replacing any value already stored for it.
+    ///
+    /// Returns without touching the disk; the writer thread appends the value later.
+    pub(crate) fn put(
+        &self,
+        key: K,
+        bytes: Vec<u8>,
+        expire_at: Option<Instant>,
+        ttl: Option<Duration>,
+    ) {
+        let mut state = self.shared.lock();
+        state.next_token += 1;
+        let token = state.next_token;
+        if let Some(old) = state.index.remove(&key) {
+            state.release(&old);
+        }
+        let staged = Staged {
+            bytes: bytes.into(),
+            expire_at,
+            ttl,
+            token,
+        };
+        state.staged.insert(key, staged);
+        self.wake(state);
+    }
+
+    /// Reads the value of `key` without removing it, if it has not expired.
+    ///
+    /// A value that has not been written yet is returned from memory; otherwise this
+    /// blocks on reading the segment file.
+    pub(crate) fn peek(&self, key: &K, now: Instant) -> Option<Spilled<Vec<u8>>> {
+        let slot = {
+            let state = self.shared.lock();
+            if let Some(staged) = state.staged.get(key) {
+                return is_live(staged.expire_at, now).then(|| Spilled {
+                    value: staged.bytes.to_vec(),
+                    expire_at: staged.expire_at,
+                    ttl: staged.ttl,
+                    token: staged.token,
+                });
+            }
+            *state
+                .index
+                .get(key)
+                .filter(|slot| is_live(slot.expire_at, now))?
+        };
+        let bytes = read_slot(&self.shared.segment_path(slot.segment), &slot).ok()?;
+        Some(Spilled {
+            value: bytes,
+            expire_at: slot.expire_at,
+            ttl: slot.ttl,
+            token: slot.token,
+        })
+    }
+
+    /// Returns `true` if `key` has a value that has not expired, without reading it.
+    pub(crate) fn contains(&self, key: &K, now: Instant) -> bool {
+        let state = self.shared.lock();
+        match state.staged.get(key) {
+            Some(staged) => is_live(staged.expire_at, now),
+            None => state
+                .index
+                .get(key)
+                .is_some_and(|slot| is_live(slot.expire_at, now)),
+        }
+    }
+
+    /// Removes `key` if it still holds the copy that [`peek`](Self::peek) returned with
+    /// `token`, and returns whether it did.
+    pub(crate) fn claim(&self, key: &K, token: u64) -> bool {
+        let mut state = self.shared.lock();
+        if state.staged.get(key).is_some_and(|s| s.token == token) {
+            state.staged.remove(key);
+            return true;
+        }
+        if state.index.get(key).is_none_or(|s| s.token != token) {
+            return false;
+        }
+        if let Some(slot) = state.index.remove(key) {
+            state.release(&slot);
+        }
+        self.wake(state);
+        true
+    }
+
+    /// Removes `key` without reading its value.
+    pub(crate) fn remove(&self, key: &K) {
+        let mut state = self.shared.lock();
+        state.staged.remove(key);
+        if let Some(slot) = state.index.remove(key) {
+            state.release(&slot);
+        }
+        self.wake(state);
+    }
+
+    /// Removes every entry; the writer deletes the segment files.
+    pub(crate) fn clear(&self) {
+        let mut state = self.shared.lock();
+        state.index.clear();
+        state.staged.clear();
+        state.victims.clear();
+        state.active = None;
+        let segments = std::mem::take(&mut state.segments);
+        state.doomed.extend(segments.into_keys());
+        self.wake(state);
+    }
+
+    /// Drops entries whose deadline has passed and reclaims emptied segments.
+    pub(crate) fn purge_expired(&self, now: Instant) {
+        let mut state = self.shared.lock();
+        state
+            .staged
+            .retain(|_, staged| is_live(staged.expire_at, now));
+        let expired: Vec<K> = state
+            .index
+            .iter()
+            .filter(|(_, slot)| !is_live(slot.expire_at, now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        for key in &expired {
+            if let Some(slot) = state.index.remove(key) {
+                state.release(&slot);
+            }
+        }
+        self.wake(state);
+    }
+
+    /// Returns the number of entries that have not expired.
+    pub(crate) fn len(&self, now: Instant) -> usize {
+        let state = self.shared.lock();
+        let staged = state
+            .staged
+            .values()
+            .filter(|staged| is_live(staged.expire_at, now))
+            .count();
+        let written = state
+            .index
+            .values()
+            .filter(|slot| is_live(slot.expire_at, now))
+            .count();
+        staged + written
+    }
+
+    /// Returns the keys of all entries that have not expired.
+    pub(crate) fn keys(&self, now: Instant) -> Vec<K> {
+        let state = self.shared.lock();
+        let staged = state
+            .staged
+            .iter()
+            .filter(|(_, staged)| is_live(staged.expire_at, now))
+            .map(|(k, _)| k.clone());
+        let written = state
+            .index
+            .iter()
+            .filter(|(_, slot)| is_live(slot.expire_at, now))
+            .map(|(k, _)| k.clone());
+        staged.chain(written).collect()
+    }
+
+    /// Blocks until the writer has written everything queued so far and deleted the
+    /// segments nothing points into.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last write if it failed. The values it could not write
+    /// stay queued in memory and are retried.
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        let mut state = self.shared.lock();
+        state.requested += 1;
+        let target = state.requested;
+        self.shared.work.notify_one();
+        while state.served < target {
+            state = self
+                .shared
+                .idle
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        match state.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Releases the state lock and wakes the writer if there is work for it.
+    fn wake(&self, state: MutexGuard<'_, DiskState<K>>) {
+        let pending =
+            !state.doomed.is_empty() || !state.victims.is_empty() || !state.staged.is_empty();
+        drop(state);
+        if pending {
+            self.shared.work.notify_one();
+        }
+    }
+}
+
+impl<K> Drop for DiskTier<K> {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.work.notify_one();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        let state = self.shared.lock();
+        for id in state.segments.keys().chain(&state.doomed) {
+            let _ = fs::remove_file(self.shared.segment_path(*id));
+        }
+    }
+}
+
+/// Object-safe view of a `DiskTier` that encodes and decodes values of type `V`.
+///
+/// This lets `MiniCache<K, V>` hold its tier without requiring `V: Codec` everywhere.
+/// Only [`peek`](Spill::peek) and [`flush`](Spill::flush) may block on I/O; the cache
+/// calls them without holding its lock.
+pub(crate) trait Spill<K, V> {
+    /// Queues an evicted entry for the writer thread.
+    fn spill(&self, key: K, value: V, expire_at: Option<Instant>, ttl: Option<Duration>);
+    fn peek(&self, key: &K, now: Instant) -> Option<Spilled<V>>;
+    fn contains(&self, key: &K, now: Instant) -> bool;
+    fn claim(&self, key: &K, token: u64) -> bool;
+    fn remove(&self, key: &K);
+    fn clear(&self);
+    fn purge_expired(&self, now: Instant);
+    fn len(&self, now: Instant) -> usize;
+    fn keys(&self, now: Instant) -> Vec<K>;
+    fn flush(&self) -> io::Result<()>;
+}
+
+impl<K, V> Spill<K, V> for DiskTier<K>
+where
+    K: Hash + Eq + Clone + Send + 'static,
+    V: Codec,
+{
+    fn spill(&self, key: K, value: V, expire_at: Option<Instant>, ttl: Option<Duration>) {
+        self.put(key, value.encode(), expire_at, ttl)
+    }
+
+    fn peek(&self, key: &K, now: Instant) -> Option<Spilled<V>> {
+        let spilled = DiskTier::peek(self, key, now)?;
+        Some(Spilled {
+            value: V::decode(&spilled.value)?,
+            expire_at: spilled.expire_at,
+            ttl: spilled.ttl,
+            token: spilled.token,
+        })
+    }
+
+    fn contains(&self, key: &K, now: Instant) -> bool {
+        DiskTier::contains(self, key, now)
+    }
+
+    fn claim(&self, key: &K, token: u64) -> bool {
+        DiskTier::claim(self, key, token)
+    }
+
+    fn remove(&self, key: &K) {
+        DiskTier::remove(self, key)
+    }
+
+    fn clear(&self) {
+        DiskTier::clear(self)
+    }
+
+    fn purge_expired(&self, now: Instant) {
+        DiskTier::purge_expired(self, now)
+    }
+
+    fn len(&self, now: Instant) -> usize {
+        DiskTier::len(self, now)
+    }
+
+    fn keys(&self, now: Instant) -> Vec<K> {
+        DiskTier::keys(self, now)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        DiskTier::flush(self)
+    }
+}
+
+fn is_live(expire_at: Option<Instant>, now: Instant) -> bool {
+    expire_at.is_none_or(|t| now < t)
+}
+
+fn read_slot(path: &Path, slot: &Slot) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut bytes = vec![0; slot.len];
+    file.seek(SeekFrom::Start(slot.offset))?;
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A directory under the system temp directory that is deleted when dropped, so
+/// tests leave nothing behind.
+#[cfg(test)]
+pub(crate) struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        TempDir(std::env::temp_dir().join(format!("minicache-{}-{}", name, std::process::id())))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn segment_count(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == SEGMENT_EXTENSION)
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_put_peek_and_claim() {
+        let dir = TempDir::new("put-peek");
+        let tier = DiskTier::open(dir.path()).unwrap();
+        let now = Instant::now();
+
+        tier.put("key1", b"value1".to_vec(), None, None);
+        tier.put("key2", b"value2".to_vec(), None, None);
+        assert_eq!(tier.len(now), 2);
+
+        // The value is readable while queued and keeps its token once written
+        let queued = tier.peek(&"key1", now).unwrap();
+        tier.flush().unwrap();
+        let written = tier.peek(&"key1", now).unwrap();
+        assert_eq!(queued.value, b"value1");
+        assert_eq!(written.value, b"value1");
+        assert_eq!(written.token, queued.token);
+
+        assert!(tier.claim(&"key1", written.token));
+        assert!(tier.peek(&"key1", now).is_none());
+        assert!(!tier.contains(&"key1", now));
+        assert_eq!(tier.len(now), 1);
+    }
+
+    #[test]
+    fn test_overwrite_keeps_latest_value() {
+        let dir = TempDir::new("overwrite");
+        let tier = DiskTier::open(dir.path()).unwrap();
+        let now = Instant::now();
+
+        tier.put("key1", b"old".to_vec(), None, None);
+        tier.flush().unwrap();
+        let old = tier.peek(&"key1", now).unwrap();
+        tier.put("key1", b"new".to_vec(), None, None);
+
+        assert_eq!(tier.len(now), 1);
+        assert!(!tier.claim(&"key1", old.token));
+        assert_eq!(tier.peek(&"key1", now).unwrap().value, b"new");
+    }
+
+    #[test]
+    fn test_expired_entries_are_not_returned() {
+        let dir = TempDir::new("expired");
+        let tier = DiskTier::open(dir.path()).unwrap();
+        let now = Instant::now();
+        let deadline = now + Duration::from_secs(1);
+
+        tier.put("key1", b"value1".to_vec(), Some(deadline), None);
+        assert_eq!(tier.keys(now), vec!["key1"]);
+
+        let later = deadline + Duration::from_millis(1);
+        assert_eq!(tier.len(later), 0);
+        assert!(!tier.contains(&"key1", later));
+        assert!(tier.peek(&"key1", later).is_none());
+    }
+
+    #[test]
+    fn test_emptied_segments_are_deleted() {
+        let dir = TempDir::new("segments");
+        let tier = DiskTier::open(dir.path()).unwrap().segment_size(8);
+
+        tier.put("key1", b"12345678".to_vec(), None, None);
+        tier.put("key2", b"12345678".to_vec(), None, None);
+        tier.put("key3", b"12345678".to_vec(), None, None);
+        tier.flush().unwrap();
+        assert_eq!(segment_count(dir.path()), 3);
+
+        let keys = tier.keys(Instant::now());
+        tier.remove(&keys[0]);
+        let second = tier.peek(&keys[1], Instant::now()).unwrap();
+        assert!(tier.claim(&keys[1], second.token));
+        tier.flush().unwrap();
+        assert!(segment_count(dir.path()) <= 2);
+
+        tier.clear();
+        tier.flush().unwrap();
+        assert_eq!(segment_count(dir.path()), 0);
+    }
+
+    #[test]
+    fn test_purge_expired_reclaims_segments() {
+        let dir = TempDir::new("purge");
+        let tier = DiskTier::open(dir.path()).unwrap().segment_size(1);
+        let now = Instant::now();
+
+        tier.put(
+            "key1",
+            b"a".to_vec(),
+            Some(now + Duration::from_millis(10)),
+            None,
+        );
+        tier.flush().unwrap();
+        tier.put("key2", b"b".to_vec(), None, None);
+        tier.flush().unwrap();
+        assert_eq!(segment_count(dir.path()), 2);
+
+        tier.purge_expired(now + Duration::from_secs(1));
+        tier.flush().unwrap();
+
+        assert_eq!(tier.len(now), 1);
+        assert_eq!(segment_count(dir.path()), 1);
+    }
+
+    #[test]
+    fn test_mostly_dead_segments_are_compacted() {
+        let dir = TempDir::new("compact");
+        let tier = DiskTier::open(dir.path()).unwrap().segment_size(24);
+        let now = Instant::now();
+
+        for key in ["a", "b", "c"] {
+            tier.put(key, key.repeat(8).into_bytes(), None, None);
+        }
+        tier.flush().unwrap();
+        tier.put("d", b"dddddddd".to_vec(), None, None);
+        tier.flush().unwrap();
+        assert_eq!(segment_count(dir.path()), 2);
+
+        // A third of the first segment is live: its last value moves to the second
+        tier.remove(&"a");
+        tier.remove(&"b");
+        tier.flush().unwrap();
+
+        assert_eq!(segment_count(dir.path()), 1);
+        assert_eq!(tier.peek(&"c", now).unwrap().value, b"cccccccc");
+        assert_eq!(tier.peek(&"d", now).unwrap().value, b"dddddddd");
+    }
+
+    #[test]
+    fn test_failed_writes_are_reported_and_retried() {
+        let dir = TempDir::new("failing");
+        let tier = DiskTier::open(dir.path()).unwrap().segment_size(1);
+        let now = Instant::now();
+
+        tier.put("key1", b"a".to_vec(), None, None);
+        tier.flush().unwrap();
+
+        // Without its directory the tier cannot start the next segment
+        fs::remove_dir_all(dir.path()).unwrap();
+        tier.put("key2", b"b".to_vec(), None, None);
+        assert!(tier.flush().is_err());
+        assert_eq!(tier.peek(&"key2", now).unwrap().value, b"b");
+
+        fs::create_dir_all(dir.path()).unwrap();
+        tier.flush().unwrap();
+        assert_eq!(segment_count(dir.path()), 1);
+        assert_eq!(tier.peek(&"key2", now).unwrap().value, b"b");
+    }
+
+    #[test]
+    fn test_codec_round_trips() {
+        assert_eq!(
+            String::decode(&"hello".to_string().encode()),
+            Some("hello".to_string())
+        );
+        assert_eq!(u64::decode(&42u64.encode()), Some(42));
+        assert_eq!(i32::decode(&[1, 2]), None);
+    }
+}
@@ -0,0 +1,244 @@
+//! A HyperLogLog cardinality estimator with Redis-style `PF*` operations on the cache.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use crate::collections::Collection;
+use crate::core::MiniCache;
+
+/// Number of index bits; `2^PRECISION` registers give a standard error of about 0.81%.
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+
+/// Estimates the number of distinct items added to it in a fixed 16 KiB.
+///
+/// Registers are allocated on the first [`add`](HyperLogLog::add), so an empty
+/// estimator costs nothing. Items are hashed with the standard library's hasher, so
+/// estimates are only comparable within one build of a program.
+///
+/// Store it as the value of a `MiniCache` to use `pfadd`, `pfcount` and `pfmerge`.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::HyperLogLog;
+///
+/// let mut visitors = HyperLogLog::new();
+/// for id in 0..10_000 {
+///     visitors.add(&id);
+///     visitors.add(&id);
+/// }
+///
+/// let estimate = visitors.count() as f64;
+/// assert!((estimate - 10_000.0).abs() < 10_000.0 * 0.05);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Creates an empty estimator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `item`, returning `true` if the estimate may have changed.
+    pub fn add<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        if self.registers.is_empty() {
+            self.registers = vec![0; REGISTERS];
+        }
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // The guard bit caps the rank when the remaining bits are all zero
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the estimated number of distinct items added.
+    pub fn count(&self) -> u64 {
+        if self.registers.is_empty() {
+            return 0;
+        }
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&r| 2f64.powi(-i32::from(r)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // Linear counting is more accurate for small cardinalities
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    /// Folds `other` into this estimator, so it counts the union of both.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.registers.is_empty() {
+            return;
+        }
+        if self.registers.is_empty() {
+            self.registers = other.registers.clone();
+            return;
+        }
+        for (mine, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+}
+
+impl Collection for HyperLogLog {
+    fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+    }
+}
+
+impl<K> MiniCache<K, HyperLogLog>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Adds `items` to the estimator at `key`, returning `true` if its estimate may
+    /// have changed (`PFADD`).
+    ///
+    /// A missing key is created with `ttl`; an existing key keeps its TTL. Like the
+    /// other collection operations, this does not reach a backing store.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{HyperLogLog, MiniCache};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<&str, HyperLogLog> = MiniCache::new(Duration::from_secs(60));
+    ///     let day = Some(Duration::from_secs(86_400));
+    ///
+    ///     cache.pfadd(&"page:home", ["alice", "bob", "alice"], day).await;
+    ///     cache.pfadd(&"page:docs", ["bob", "carol"], day).await;
+    ///     assert_eq!(cache.pfcount(&"page:home").await, 2);
+    ///
+    ///     cache.pfmerge(&"site", &["page:home", "page:docs"], day).await;
+    ///     assert_eq!(cache.pfcount(&"site").await, 3);
+    /// }
+    /// ```
+    pub async fn pfadd<T, I>(&self, key: &K, items: I, ttl: Option<Duration>) -> bool
+    where
+        T: Hash,
+        I: IntoIterator<Item = T>,
+    {
+        self.mutate(key, Some(ttl), |hll| {
+            items
+                .into_iter()
+                .fold(false, |changed, item| hll.add(&item) | changed)
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    /// Returns the estimated number of distinct items added at `key` (`PFCOUNT`).
+    pub async fn pfcount(&self, key: &K) -> u64 {
+        self.inspect(key, HyperLogLog::count).await.unwrap_or(0)
+    }
+
+    /// Merges the estimators at `sources` into the one at `dest` (`PFMERGE`).
+    ///
+    /// A missing `dest` is created with `ttl`; an existing one keeps its TTL. Missing
+    /// sources are skipped. Each source is read separately, so the merge is not atomic
+    /// with respect to concurrent `pfadd` calls on the sources.
+    pub async fn pfmerge(&self, dest: &K, sources: &[K], ttl: Option<Duration>) {
+        let mut union = HyperLogLog::new();
+        for source in sources {
+            if let Some(hll) = self.inspect(source, HyperLogLog::clone).await {
+                union.merge(&hll);
+            }
+        }
+        self.mutate(dest, Some(ttl), |hll| hll.merge(&union)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn within(estimate: u64, actual: u64, tolerance: f64) -> bool {
+        (estimate as f64 - actual as f64).abs() <= actual as f64 * tolerance
+    }
+
+    #[test]
+    fn test_estimates_across_cardinalities() {
+        let mut hll = HyperLogLog::new();
+        assert_eq!(hll.count(), 0);
+        assert!(Collection::is_empty(&hll));
+
+        let mut added = 0u64;
+        for target in [10u64, 1_000, 100_000] {
+            while added < target {
+                hll.add(&added);
+                added += 1;
+            }
+            assert!(
+                within(hll.count(), target, 0.03),
+                "{} vs {target}",
+                hll.count()
+            );
+        }
+        assert_eq!(hll.registers.len(), REGISTERS);
+    }
+
+    #[test]
+    fn test_duplicates_and_merge() {
+        let (mut a, mut b) = (HyperLogLog::new(), HyperLogLog::new());
+        for i in 0..5_000u32 {
+            a.add(&i);
+            b.add(&(i + 2_500));
+        }
+        assert!(!a.add(&0u32));
+
+        let mut union = HyperLogLog::new();
+        union.merge(&a);
+        union.merge(&b);
+        union.merge(&HyperLogLog::new());
+        assert!(within(union.count(), 7_500, 0.03));
+        assert_eq!(union.count(), {
+            let mut both = a.clone();
+            both.merge(&b);
+            both.count()
+        });
+    }
+
+    #[tokio::test]
+    async fn test_cache_pf_operations() {
+        let cache: MiniCache<&str, HyperLogLog> = MiniCache::new(Duration::from_secs(1));
+
+        assert!(cache.pfadd(&"a", 0..1_000, None).await);
+        assert!(!cache.pfadd(&"a", 0..1_000, None).await);
+        cache.pfadd(&"b", 500..1_500, None).await;
+        assert!(within(cache.pfcount(&"a").await, 1_000, 0.03));
+        assert_eq!(cache.pfcount(&"missing").await, 0);
+
+        cache.pfmerge(&"all", &["a", "b", "missing"], None).await;
+        assert!(within(cache.pfcount(&"all").await, 1_500, 0.03));
+
+        cache.pfmerge(&"none", &["missing"], None).await;
+        assert!(!cache.contains(&"none").await);
+        assert!(!cache.pfadd(&"empty", Vec::<u32>::new(), None).await);
+        assert!(!cache.contains(&"empty").await);
+    }
+}
@@ -0,0 +1,241 @@
+//! Async streams over the entries of a `MiniCache`, returned by [`MiniCache::iter`] and
+//! [`MiniCache::values`].
+
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::core::MiniCache;
+
+/// Number of entries fetched per lock acquisition unless configured otherwise.
+const DEFAULT_BATCH_SIZE: usize = 64;
+
+type Batch<K, V> = Pin<Box<dyn Future<Output = (u64, Vec<(K, V)>)> + Send>>;
+
+/// A stream of the `(key, value)` pairs in a cache.
+///
+/// Entries are fetched lazily with [`MiniCache::scan`], one batch at a time, so the
+/// cache lock is only held while a batch is copied and a slow consumer applies
+/// backpressure instead of buffering the whole cache. The stream has the guarantees of
+/// `scan`: entries that stay in the cache while it runs are yielded exactly once, and
+/// entries that currently live in the disk tier are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::MiniCache;
+/// use std::time::Duration;
+/// use tokio_stream::StreamExt;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let cache = MiniCache::new(Duration::from_secs(60));
+///     cache.set("key1", 1, None).await;
+///     cache.set("key2", 2, None).await;
+///
+///     let mut total = 0;
+///     let mut entries = cache.iter();
+///     while let Some((_key, value)) = entries.next().await {
+///         total += value;
+///     }
+///     assert_eq!(total, 3);
+/// }
+/// ```
+pub struct Iter<K, V> {
+    cache: MiniCache<K, V>,
+    cursor: u64,
+    done: bool,
+    batch_size: usize,
+    buffer: VecDeque<(K, V)>,
+    pending: Option<Batch<K, V>>,
+}
+
+impl<K, V> Iter<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub(crate) fn new(cache: MiniCache<K, V>) -> Self {
+        Iter {
+            cache,
+            cursor: 0,
+            done: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    /// Sets how many entries are visited per lock acquisition. Defaults to 64.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+// Nothing in `Iter` is structurally pinned
+impl<K, V> Unpin for Iter<K, V> {}
+
+impl<K, V> Stream for Iter<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    type Item = (K, V);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(entry) = this.buffer.pop_front() {
+                return Poll::Ready(Some(entry));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let pending = this.pending.get_or_insert_with(|| {
+                let cache = this.cache.clone();
+                let (cursor, count) = (this.cursor, this.batch_size);
+                Box::pin(async move { cache.scan(cursor, count).await })
+            });
+            let (next, batch) = match pending.as_mut().poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.pending = None;
+            this.cursor = next;
+            this.done = next == 0;
+            this.buffer.extend(batch);
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Iter<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iter")
+            .field("cursor", &self.cursor)
+            .field("done", &self.done)
+            .field("batch_size", &self.batch_size)
+            .field("buffered", &self.buffer.len())
+            .finish()
+    }
+}
+
+/// A stream of the values in a cache.
+///
+/// Works like [`Iter`], yielding only the values.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::MiniCache;
+/// use std::time::Duration;
+/// use tokio_stream::StreamExt;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let cache = MiniCache::new(Duration::from_secs(60));
+///     cache.set("key1", 1, None).await;
+///     cache.set("key2", 2, None).await;
+///
+///     let mut values: Vec<i32> = cache.values().collect().await;
+///     values.sort();
+///     assert_eq!(values, vec![1, 2]);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Values<K, V> {
+    inner: Iter<K, V>,
+}
+
+impl<K, V> Values<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub(crate) fn new(cache: MiniCache<K, V>) -> Self {
+        Values {
+            inner: Iter::new(cache),
+        }
+    }
+
+    /// Sets how many entries are visited per lock acquisition. Defaults to 64.
+    pub fn batch_size(self, batch_size: usize) -> Self {
+        Values {
+            inner: self.inner.batch_size(batch_size),
+        }
+    }
+}
+
+impl<K, V> Stream for Values<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    type Item = V;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_next(cx)
+            .map(|entry| entry.map(|(_, value)| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_iter_yields_every_entry() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        for i in 0..200 {
+            cache.set(i, i * 3, None).await;
+        }
+
+        let mut entries: Vec<(u32, u32)> = cache.iter().batch_size(16).collect().await;
+        entries.sort();
+
+        assert_eq!(entries, (0..200).map(|i| (i, i * 3)).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_iter_on_empty_cache() {
+        let cache: MiniCache<u32, u32> = MiniCache::new(Duration::from_secs(1));
+
+        assert_eq!(cache.iter().next().await, None);
+        assert_eq!(cache.values().next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_iter_does_not_hold_the_lock_between_batches() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        for i in 0..10 {
+            cache.set(i, i, None).await;
+        }
+
+        let mut entries = cache.entries().batch_size(2);
+        entries.next().await;
+        // Writing mid-stream must not deadlock
+        cache.set(100, 100, None).await;
+        let rest: Vec<_> = entries.collect().await;
+
+        assert!(rest.len() >= 9);
+    }
+
+    #[tokio::test]
+    async fn test_values() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        cache.set("key1", "value1", None).await;
+        cache.set("key2", "value2", None).await;
+
+        let mut values: Vec<&str> = cache.values().batch_size(1).collect().await;
+        values.sort();
+
+        assert_eq!(values, vec!["value1", "value2"]);
+    }
+}
@@ -0,0 +1,79 @@
+//! Per-key async mutexes, created on demand and dropped once nobody holds or awaits them.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// One async mutex per key that is currently locked.
+pub(crate) struct KeyLocks<K> {
+    map: Mutex<HashMap<K, Arc<AsyncMutex<()>>>>,
+}
+
+impl<K> Default for KeyLocks<K> {
+    fn default() -> Self {
+        KeyLocks {
+            map: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K> KeyLocks<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Waits until no other task holds `key`, then holds it until the guard is dropped.
+    pub(crate) async fn lock(&self, key: &K) -> KeyGuard<'_, K> {
+        let lock = self.map().entry(key.clone()).or_default().clone();
+        KeyGuard {
+            locks: self,
+            key: key.clone(),
+            _guard: lock.lock_owned().await,
+        }
+    }
+
+    fn map(&self) -> MutexGuard<'_, HashMap<K, Arc<AsyncMutex<()>>>> {
+        self.map.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Exclusive hold on one key of a [`KeyLocks`].
+pub(crate) struct KeyGuard<'a, K: Hash + Eq + Clone> {
+    locks: &'a KeyLocks<K>,
+    key: K,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl<K: Hash + Eq + Clone> Drop for KeyGuard<'_, K> {
+    fn drop(&mut self) {
+        let mut map = self.locks.map();
+        // Only the map and this guard hold the mutex, so nobody is waiting for it
+        if map
+            .get(&self.key)
+            .is_some_and(|lock| Arc::strong_count(lock) == 2)
+        {
+            map.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_locks_are_per_key_and_forgotten() {
+        let locks = KeyLocks::default();
+        let first = locks.lock(&1).await;
+        let _other = locks.lock(&2).await;
+
+        let busy = tokio::time::timeout(Duration::ZERO, locks.lock(&1)).await;
+        assert!(busy.is_err());
+        drop(first);
+        drop(locks.lock(&1).await);
+
+        // Only the key still held keeps its mutex
+        assert_eq!(locks.map().len(), 1);
+    }
+}
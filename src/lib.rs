@@ -107,6 +107,38 @@
 //! - **Concurrent Access**: ~1.7M operations/second
 //! - **Memory Overhead**: ~162 bytes per entry
 
+pub mod bloom;
+pub mod builder;
+pub mod cache;
+pub mod clock;
+mod collections;
 pub mod core;
+pub mod disk;
+pub mod hyperloglog;
+pub mod iter;
+mod keylock;
+pub mod loader;
+pub mod lock;
+pub mod namespace;
+mod pattern;
+pub mod pubsub;
+pub mod ratelimit;
+mod rng;
+pub mod sorted_set;
+pub mod store;
 
+pub use bloom::BloomFilter;
+pub use builder::{Jitter, MiniCacheBuilder};
+pub use cache::{Cache, DEFAULT_PROMOTE_TTL, NoopCache, TieredCache};
+pub use clock::{Clock, MockClock, SystemClock};
 pub use core::MiniCache;
+pub use disk::{Codec, DiskTier};
+pub use hyperloglog::HyperLogLog;
+pub use iter::{Iter, Values};
+pub use loader::{LoaderError, Lookup, StalePolicy};
+pub use lock::{LockGuard, LockManager};
+pub use namespace::{Namespace, NamespaceOptions, NamespaceStats};
+pub use pubsub::{Message, Subscription};
+pub use ratelimit::{Algorithm, Decision, RateLimiter};
+pub use sorted_set::SortedSet;
+pub use store::{MemoryStore, Store, StoreError, WriteBehind};
@@ -0,0 +1,91 @@
+//! Loader functions that compute values for the cache on demand.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Error type returned by loader functions.
+pub type LoaderError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Type alias for the boxed future returned by a loader.
+pub(crate) type LoadFuture<V> = Pin<Box<dyn Future<Output = Result<V, LoaderError>> + Send>>;
+
+/// Type alias for a type-erased loader function.
+pub(crate) type Loader<K, V> = Arc<dyn Fn(K) -> LoadFuture<V> + Send + Sync>;
+
+/// Erases the concrete type of a loader closure.
+pub(crate) fn boxed<K, V, F, Fut, E>(loader: F) -> Loader<K, V>
+where
+    F: Fn(K) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<V, E>> + Send + 'static,
+    E: Into<LoaderError>,
+{
+    Arc::new(move |key| {
+        let fut = loader(key);
+        Box::pin(async move { fut.await.map_err(Into::into) })
+    })
+}
+
+/// Refresh-ahead settings: how long after a write an entry becomes due for a
+/// background reload, and the loader used to reload it.
+pub(crate) struct Refresh<K, V> {
+    pub(crate) after: Duration,
+    pub(crate) loader: Loader<K, V>,
+}
+
+/// How long a value computed by [`MiniCache::get_with`] stays fresh, and how much longer
+/// it may be served stale.
+///
+/// [`MiniCache::get_with`]: crate::MiniCache::get_with
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::StalePolicy;
+/// use std::time::Duration;
+///
+/// // Fresh for 30 seconds, then served stale for up to 5 more minutes
+/// let policy = StalePolicy::new(Duration::from_secs(30), Duration::from_secs(300));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StalePolicy {
+    pub(crate) fresh: Duration,
+    pub(crate) stale: Duration,
+}
+
+impl StalePolicy {
+    /// Creates a policy with a fresh TTL and a stale window that starts when it ends.
+    pub fn new(fresh: Duration, stale: Duration) -> Self {
+        StalePolicy { fresh, stale }
+    }
+}
+
+/// The value returned by [`MiniCache::get_with`], tagged with where it came from.
+///
+/// [`MiniCache::get_with`]: crate::MiniCache::get_with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup<V> {
+    /// The cached value is within its fresh TTL.
+    Fresh(V),
+    /// The cached value is past its fresh TTL and is being revalidated.
+    Stale(V),
+    /// The value was missing and has just been computed by the loader.
+    Loaded(V),
+}
+
+impl<V> Lookup<V> {
+    /// Returns a reference to the value.
+    pub fn value(&self) -> &V {
+        match self {
+            Lookup::Fresh(v) | Lookup::Stale(v) | Lookup::Loaded(v) => v,
+        }
+    }
+
+    /// Returns the value, discarding where it came from.
+    pub fn into_value(self) -> V {
+        match self {
+            Lookup::Fresh(v) | Lookup::Stale(v) | Lookup::Loaded(v) => v,
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Lease locks with fencing tokens on top of `MiniCache`.
+
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::builder::MiniCacheBuilder;
+use crate::clock::Clock;
+use crate::core::MiniCache;
+
+/// How often expired leases are purged; reads already ignore them in between.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The holder of a lock, as stored in the manager's cache.
+#[derive(Clone)]
+struct Lease {
+    owner: Arc<str>,
+    token: u64,
+}
+
+/// Hands out mutually exclusive, time-limited locks on keys.
+///
+/// A lock is a lease: it is released by [`unlock`](LockGuard::unlock) or automatically
+/// once its lease ends, so a holder that crashes cannot block a key forever. Holders of
+/// long tasks should [`extend`](LockGuard::extend) the lease before it runs out.
+///
+/// Every acquisition gets a fencing token, larger than any token handed out before by
+/// this manager. A holder whose lease ran out without it noticing still has its old,
+/// smaller token, so a resource that remembers the largest token it has seen can reject
+/// its late writes. Clones share the same locks.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::LockManager;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let locks = LockManager::new();
+///     let lease = Duration::from_secs(30);
+///
+///     let guard = locks.try_lock("job:7", "worker-1", lease).await.unwrap();
+///     // Only one worker gets the job
+///     assert!(locks.try_lock("job:7", "worker-2", lease).await.is_none());
+///
+///     assert!(guard.unlock().await);
+///     let next = locks.try_lock("job:7", "worker-2", lease).await.unwrap();
+///     assert!(next.token() > 1);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct LockManager<K> {
+    cache: MiniCache<K, Lease>,
+    tokens: Arc<AtomicU64>,
+}
+
+impl<K> LockManager<K>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Creates a lock manager with no locks held.
+    pub fn new() -> Self {
+        Self::from_builder(MiniCache::builder(CLEANUP_INTERVAL))
+    }
+
+    /// Creates a lock manager that reads the time from `clock`.
+    ///
+    /// Useful with a [`MockClock`](crate::MockClock) to test lease expiry without
+    /// waiting.
+    pub fn with_clock<C>(clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        Self::from_builder(MiniCache::builder(CLEANUP_INTERVAL).clock(clock))
+    }
+
+    fn from_builder(builder: MiniCacheBuilder<K, Lease>) -> Self {
+        LockManager {
+            cache: builder.build(),
+            tokens: Arc::default(),
+        }
+    }
+
+    /// Acquires the lock on `key` for `owner` unless someone holds it.
+    ///
+    /// Checking and taking the lock is a single atomic step, so of several concurrent
+    /// callers exactly one succeeds. Locks are not reentrant: an owner that already
+    /// holds `key` cannot acquire it again.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to lock
+    /// * `owner` - Who takes the lock, such as a worker ID
+    /// * `lease` - How long the lock is held unless extended or unlocked
+    ///
+    /// # Returns
+    ///
+    /// A guard carrying the lock's fencing token, or `None` if the key is locked
+    pub async fn try_lock(&self, key: K, owner: &str, lease: Duration) -> Option<LockGuard<K>> {
+        let token = self.tokens.fetch_add(1, Ordering::Relaxed) + 1;
+        let owner: Arc<str> = owner.into();
+        let requested = Lease {
+            owner: owner.clone(),
+            token,
+        };
+        let holder = self
+            .cache
+            .insert_if_absent(key.clone(), requested, Some(lease))
+            .await;
+        (holder.token == token).then(|| LockGuard {
+            manager: self.clone(),
+            key,
+            owner,
+            token,
+        })
+    }
+
+    /// Restarts the lease on `key` from now if `owner` holds it.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the lease was extended, `false` if `owner` does not hold the lock
+    pub async fn extend(&self, key: &K, owner: &str, lease: Duration) -> bool {
+        self.cache
+            .expire_if(key, lease, |held| &*held.owner == owner)
+            .await
+    }
+
+    /// Releases the lock on `key` if `owner` holds it.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the lock was released, `false` if `owner` does not hold it
+    pub async fn unlock(&self, key: &K, owner: &str) -> bool {
+        self.cache
+            .remove_if(key, |held| &*held.owner == owner)
+            .await
+    }
+
+    /// Returns the owner and fencing token of the lock on `key`, if it is held.
+    pub async fn holder(&self, key: &K) -> Option<(String, u64)> {
+        let lease = self.cache.get(key).await?;
+        Some((lease.owner.to_string(), lease.token))
+    }
+
+    /// Returns `true` if the lock on `key` is held.
+    pub async fn is_locked(&self, key: &K) -> bool {
+        self.cache.contains(key).await
+    }
+}
+
+impl<K> Default for LockManager<K>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> fmt::Debug for LockManager<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockManager")
+            .field("last_token", &self.tokens.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// A lock acquired with [`LockManager::try_lock`].
+///
+/// Dropping the guard does not release the lock; call [`unlock`](Self::unlock), or
+/// let the lease run out. The guard only acts on the acquisition it was created for:
+/// once the lease has ended and the key was locked again, even by the same owner,
+/// [`extend`](Self::extend) and [`unlock`](Self::unlock) return `false`.
+#[must_use = "the lock stays held until it is unlocked or its lease ends"]
+pub struct LockGuard<K> {
+    manager: LockManager<K>,
+    key: K,
+    owner: Arc<str>,
+    token: u64,
+}
+
+impl<K> LockGuard<K>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Returns the locked key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the owner the lock was taken for.
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Returns the fencing token of this acquisition.
+    ///
+    /// Pass it along with every write made under the lock, so the receiving side can
+    /// reject writes carrying a smaller token than one it has already seen.
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+
+    /// Restarts the lease from now, returning `false` if the lock was lost.
+    pub async fn extend(&self, lease: Duration) -> bool {
+        self.manager
+            .cache
+            .expire_if(&self.key, lease, |held| held.token == self.token)
+            .await
+    }
+
+    /// Returns `true` if this acquisition still holds the lock.
+    pub async fn is_held(&self) -> bool {
+        self.manager
+            .cache
+            .get(&self.key)
+            .await
+            .is_some_and(|held| held.token == self.token)
+    }
+
+    /// Releases the lock, returning `false` if it had already been lost.
+    pub async fn unlock(self) -> bool {
+        self.manager
+            .cache
+            .remove_if(&self.key, |held| held.token == self.token)
+            .await
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for LockGuard<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockGuard")
+            .field("key", &self.key)
+            .field("owner", &self.owner)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[tokio::test]
+    async fn test_only_one_owner_at_a_time() {
+        let locks = LockManager::new();
+        let lease = Duration::from_secs(10);
+
+        let guard = locks.try_lock("job", "a", lease).await.unwrap();
+        assert!(locks.try_lock("job", "a", lease).await.is_none());
+        assert!(locks.try_lock("job", "b", lease).await.is_none());
+        assert_eq!(
+            locks.holder(&"job").await,
+            Some(("a".to_string(), guard.token()))
+        );
+
+        assert!(!locks.unlock(&"job", "b").await);
+        assert!(locks.is_locked(&"job").await);
+        assert!(locks.unlock(&"job", "a").await);
+        assert!(!guard.unlock().await);
+        assert!(!locks.is_locked(&"job").await);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_try_lock_has_one_winner() {
+        let locks = LockManager::new();
+        let attempts: Vec<_> = (0..16)
+            .map(|i| {
+                let locks = locks.clone();
+                tokio::spawn(async move {
+                    let owner = format!("worker-{i}");
+                    locks
+                        .try_lock(7, &owner, Duration::from_secs(10))
+                        .await
+                        .is_some()
+                })
+            })
+            .collect();
+
+        let mut winners = 0;
+        for attempt in attempts {
+            winners += usize::from(attempt.await.unwrap());
+        }
+        assert_eq!(winners, 1);
+    }
+
+    #[tokio::test]
+    async fn test_leases_expire_and_tokens_fence_stale_holders() {
+        let clock = MockClock::new();
+        let locks = LockManager::with_clock(clock.clone());
+        let lease = Duration::from_secs(10);
+
+        let first = locks.try_lock("job", "a", lease).await.unwrap();
+        clock.advance(Duration::from_secs(5));
+        assert!(first.extend(lease).await);
+        clock.advance(Duration::from_secs(9));
+        assert!(first.is_held().await);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(!first.is_held().await);
+        assert!(!locks.extend(&"job", "a", lease).await);
+
+        // The same owner locking again gets a new token; the old guard is fenced off
+        let second = locks.try_lock("job", "a", lease).await.unwrap();
+        assert!(second.token() > first.token());
+        assert!(!first.extend(lease).await);
+        assert!(!first.unlock().await);
+        assert!(locks.extend(&"job", "a", lease).await);
+        assert!(second.unlock().await);
+    }
+}
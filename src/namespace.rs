@@ -0,0 +1,448 @@
+//! Isolated keyspaces within one `MiniCache`, created with [`MiniCache::namespace`].
+
+use async_trait::async_trait;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::cache::Cache;
+use crate::core::MiniCache;
+
+/// Optional settings for a namespace, passed to [`MiniCache::namespace_with`].
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{MiniCache, NamespaceOptions};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let cache: MiniCache<u64, String> = MiniCache::new(Duration::from_secs(60));
+///     let sessions = cache.namespace_with(
+///         "sessions",
+///         NamespaceOptions::new()
+///             .default_ttl(Duration::from_secs(1800))
+///             .capacity(10_000),
+///     );
+///
+///     sessions.set(1, "alice".to_string(), None).await;
+///     assert!(sessions.ttl(&1).await.flatten().is_some());
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NamespaceOptions {
+    pub(crate) default_ttl: Option<Duration>,
+    pub(crate) capacity: Option<usize>,
+}
+
+impl NamespaceOptions {
+    /// Creates options with no default TTL and no capacity quota.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the TTL used when `set` is called without one.
+    pub fn default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    /// Limits the number of entries the namespace keeps, evicting the least recently
+    /// used entry once the quota is reached.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+}
+
+/// Hit and miss counters of a [`Namespace`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NamespaceStats {
+    /// Number of `get` calls that found a value.
+    pub hits: u64,
+    /// Number of `get` calls that found nothing.
+    pub misses: u64,
+}
+
+impl NamespaceStats {
+    /// Returns the fraction of `get` calls that were hits, or `0.0` before any call.
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// A handle to one keyspace of a [`MiniCache`].
+///
+/// Keys in a namespace never collide with keys in the parent cache or in other
+/// namespaces, and `clear`, `len` and [`stats`](Self::stats) only see the
+/// namespace's own entries. Namespaces share the parent's clock, key ordering, TTL
+/// jitter, random seed and early expiration settings, but not its disk tier, backing
+/// store or refresh loader.
+///
+/// Namespaces live until [`MiniCache::remove_namespace`] removes them.
+///
+/// A namespace dereferences to its own [`MiniCache`], so every cache method is
+/// available on it. It adds hit and miss counting to [`get`](Self::get), and applies
+/// its default TTL to [`set`](Self::set), [`set_tagged`](Self::set_tagged),
+/// [`set_if_absent`](Self::set_if_absent), [`set_if_present`](Self::set_if_present)
+/// and the counters; other writes, such as `try_set`, use the TTL they are given.
+///
+/// Handles are cheap to clone, and asking the parent for the same name again returns
+/// a handle to the same keyspace.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::MiniCache;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let cache = MiniCache::new(Duration::from_secs(60));
+///     let users = cache.namespace("users");
+///     let orders = cache.namespace("orders");
+///
+///     users.set(123, "alice", None).await;
+///     orders.set(123, "order-7", None).await;
+///
+///     assert_eq!(users.get(&123).await, Some("alice"));
+///     assert_eq!(orders.get(&123).await, Some("order-7"));
+///     assert_eq!(cache.get(&123).await, None);
+///
+///     users.clear().await;
+///     assert_eq!(orders.len().await, 1);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Namespace<K, V> {
+    name: Arc<str>,
+    cache: MiniCache<K, V>,
+    default_ttl: Option<Duration>,
+    counters: Arc<Counters>,
+}
+
+impl<K, V> Namespace<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub(crate) fn new(name: &str, cache: MiniCache<K, V>, options: NamespaceOptions) -> Self {
+        Namespace {
+            name: name.into(),
+            cache,
+            default_ttl: options.default_ttl,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Returns the name of the namespace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Stores a key-value pair in the namespace.
+    ///
+    /// A `ttl` of `None` falls back to the namespace's default TTL, if it has one.
+    pub async fn set(&self, key: K, value: V, ttl: Option<Duration>) {
+        self.cache.set(key, value, ttl.or(self.default_ttl)).await
+    }
+
+    /// Stores a key-value pair with tags, as [`MiniCache::set_tagged`] does.
+    ///
+    /// A `ttl` of `None` falls back to the namespace's default TTL, if it has one.
+    pub async fn set_tagged<I>(&self, key: K, value: V, ttl: Option<Duration>, tags: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.cache
+            .set_tagged(key, value, ttl.or(self.default_ttl), tags)
+            .await
+    }
+
+    /// Retrieves a value from the namespace, counting a hit or a miss.
+    pub async fn get(&self, key: &K) -> Option<V> {
+        let value = self.cache.get(key).await;
+        let counter = match value {
+            Some(_) => &self.counters.hits,
+            None => &self.counters.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Stores a key-value pair only if the key is absent, as
+    /// [`MiniCache::set_if_absent`] does.
+    ///
+    /// A `ttl` of `None` falls back to the namespace's default TTL, if it has one.
+    pub async fn set_if_absent(&self, key: K, value: V, ttl: Option<Duration>) -> bool {
+        self.cache
+            .set_if_absent(key, value, ttl.or(self.default_ttl))
+            .await
+    }
+
+    /// Stores a key-value pair only if the key is present, as
+    /// [`MiniCache::set_if_present`] does.
+    ///
+    /// A `ttl` of `None` falls back to the namespace's default TTL, if it has one.
+    pub async fn set_if_present(&self, key: K, value: V, ttl: Option<Duration>) -> bool {
+        self.cache
+            .set_if_present(key, value, ttl.or(self.default_ttl))
+            .await
+    }
+
+    /// Returns the hit and miss counters of the namespace.
+    pub fn stats(&self) -> NamespaceStats {
+        NamespaceStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets the hit and miss counters to zero.
+    pub fn reset_stats(&self) {
+        self.counters.hits.store(0, Ordering::Relaxed);
+        self.counters.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+impl<K> Namespace<K, i64>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Atomically adds to a counter, as [`MiniCache::incr`] does.
+    ///
+    /// A `ttl` of `None` falls back to the namespace's default TTL, if it has one.
+    pub async fn incr(&self, key: &K, delta: i64, ttl: Option<Duration>) -> i64 {
+        self.cache.incr(key, delta, ttl.or(self.default_ttl)).await
+    }
+
+    /// Atomically subtracts from a counter, as [`MiniCache::decr`] does.
+    ///
+    /// A `ttl` of `None` falls back to the namespace's default TTL, if it has one.
+    pub async fn decr(&self, key: &K, delta: i64, ttl: Option<Duration>) -> i64 {
+        self.cache.decr(key, delta, ttl.or(self.default_ttl)).await
+    }
+}
+
+impl<K> Namespace<K, f64>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Atomically adds to a floating-point counter, as [`MiniCache::incr_float`] does.
+    ///
+    /// A `ttl` of `None` falls back to the namespace's default TTL, if it has one.
+    pub async fn incr_float(&self, key: &K, delta: f64, ttl: Option<Duration>) -> f64 {
+        self.cache
+            .incr_float(key, delta, ttl.or(self.default_ttl))
+            .await
+    }
+}
+
+impl<K, V> Deref for Namespace<K, V> {
+    type Target = MiniCache<K, V>;
+
+    fn deref(&self) -> &MiniCache<K, V> {
+        &self.cache
+    }
+}
+
+#[async_trait]
+impl<K, V> Cache<K, V> for Namespace<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    async fn set(&self, key: K, value: V, ttl: Option<Duration>) {
+        Namespace::set(self, key, value, ttl).await
+    }
+
+    async fn get(&self, key: &K) -> Option<V> {
+        Namespace::get(self, key).await
+    }
+
+    async fn remove(&self, key: &K) {
+        self.cache.remove(key).await
+    }
+
+    async fn clear(&self) {
+        self.cache.clear().await
+    }
+
+    async fn contains(&self, key: &K) -> bool {
+        self.cache.contains(key).await
+    }
+
+    async fn len(&self) -> usize {
+        self.cache.len().await
+    }
+
+    async fn is_empty(&self) -> bool {
+        self.cache.is_empty().await
+    }
+
+    async fn keys(&self) -> Vec<K> {
+        self.cache.keys().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::Jitter;
+    use crate::clock::MockClock;
+
+    #[tokio::test]
+    async fn test_namespaces_isolate_keys() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        let users = cache.namespace("users");
+        let orders = cache.namespace("orders");
+
+        cache.set("id", "root", None).await;
+        users.set("id", "alice", None).await;
+        orders.set("id", "order-7", None).await;
+
+        assert_eq!(cache.get(&"id").await, Some("root"));
+        assert_eq!(users.get(&"id").await, Some("alice"));
+        assert_eq!(orders.get(&"id").await, Some("order-7"));
+
+        users.remove(&"id").await;
+        assert_eq!(users.get(&"id").await, None);
+        assert_eq!(orders.get(&"id").await, Some("order-7"));
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_same_name_returns_same_keyspace() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+
+        cache.namespace("users").set("key1", "value1", None).await;
+
+        let users = cache.namespace("users");
+        assert_eq!(users.name(), "users");
+        assert_eq!(users.get(&"key1").await, Some("value1"));
+    }
+
+    #[tokio::test]
+    async fn test_namespace_clear_and_len_are_scoped() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        let users = cache.namespace("users");
+        let orders = cache.namespace("orders");
+
+        users.set("key1", "value1", None).await;
+        users.set("key2", "value2", None).await;
+        orders.set("key1", "value1", None).await;
+        cache.set("key1", "value1", None).await;
+
+        assert_eq!(users.len().await, 2);
+        users.clear().await;
+
+        assert!(users.is_empty().await);
+        assert_eq!(orders.len().await, 1);
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_tags_are_scoped() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        let users = cache.namespace("users");
+
+        users.set_tagged("key1", "value1", None, ["a"]).await;
+        cache.set_tagged("key1", "value1", None, ["a"]).await;
+
+        assert_eq!(users.invalidate_tag("a").await, 1);
+        assert_eq!(cache.get(&"key1").await, Some("value1"));
+    }
+
+    #[tokio::test]
+    async fn test_namespace_stats() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        let users = cache.namespace("users");
+        let orders = cache.namespace("orders");
+
+        users.set("key1", "value1", None).await;
+        users.get(&"key1").await;
+        users.get(&"key1").await;
+        users.get(&"missing").await;
+        orders.get(&"key1").await;
+
+        assert_eq!(users.stats(), NamespaceStats { hits: 2, misses: 1 });
+        assert_eq!(orders.stats(), NamespaceStats { hits: 0, misses: 1 });
+        assert!((users.stats().hit_ratio() - 2.0 / 3.0).abs() < 1e-9);
+
+        users.reset_stats();
+        assert_eq!(users.stats(), NamespaceStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_namespace_default_ttl() {
+        let clock = MockClock::new();
+        let cache = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .build();
+        let sessions = cache.namespace_with(
+            "sessions",
+            NamespaceOptions::new().default_ttl(Duration::from_secs(10)),
+        );
+
+        sessions.set("short", "value", None).await;
+        sessions
+            .set("long", "value", Some(Duration::from_secs(60)))
+            .await;
+
+        clock.advance(Duration::from_secs(10));
+
+        assert_eq!(sessions.get(&"short").await, None);
+        assert_eq!(sessions.get(&"long").await, Some("value"));
+    }
+
+    #[tokio::test]
+    async fn test_namespace_capacity_quota() {
+        let cache = MiniCache::new(Duration::from_secs(1));
+        let small = cache.namespace_with("small", NamespaceOptions::new().capacity(2));
+
+        small.set("key1", "value1", None).await;
+        small.set("key2", "value2", None).await;
+        small.set("key3", "value3", None).await;
+        cache.set("key1", "value1", None).await;
+        cache.set("key2", "value2", None).await;
+        cache.set("key3", "value3", None).await;
+
+        assert_eq!(small.len().await, 2);
+        assert_eq!(small.get(&"key1").await, None);
+        assert_eq!(cache.len().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_inherits_seed() {
+        let build = || {
+            MiniCache::<u32, u32>::builder(Duration::from_secs(1))
+                .clock(MockClock::new())
+                .ttl_jitter(Jitter::Absolute(Duration::from_secs(5)))
+                .seed(42)
+                .build()
+                .namespace("jittered")
+        };
+        let (a, b) = (build(), build());
+
+        for key in 0..10 {
+            a.set(key, key, Some(Duration::from_secs(60))).await;
+            b.set(key, key, Some(Duration::from_secs(60))).await;
+            assert_eq!(a.ttl(&key).await, b.ttl(&key).await);
+        }
+    }
+}
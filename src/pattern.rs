@@ -0,0 +1,120 @@
+//! Glob-style pattern matching for string keys.
+
+/// Returns `true` if `text` matches the glob `pattern`.
+///
+/// Supported syntax:
+///
+/// * `*` matches any sequence of characters, including none
+/// * `?` matches exactly one character
+/// * `[abc]` matches one of the listed characters, `[a-z]` a range, and `[!abc]` or
+///   `[^abc]` any character not listed
+/// * `\` escapes the next character
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_class(&pattern, p, text[t]),
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(p + 2),
+            Some(&c) => (c == text[t]).then_some(p + 1),
+            None => None,
+        };
+        match (step, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                t += 1;
+            }
+            (None, Some((star, matched))) => {
+                p = star;
+                t = matched + 1;
+                backtrack = Some((star, matched + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the character class starting at `pattern[start] == '['`.
+///
+/// Returns the pattern position after the class on a match. An unterminated class is
+/// treated as a literal `[`.
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<usize> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() && (pattern[i] != ']' || first) {
+        first = false;
+        let mut low = pattern[i];
+        if low == '\\' && i + 1 < pattern.len() {
+            i += 1;
+            low = pattern[i];
+        }
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&h| h != ']') {
+            let high = pattern[i + 2];
+            matched |= low <= c && c <= high;
+            i += 3;
+        } else {
+            matched |= low == c;
+            i += 1;
+        }
+    }
+    if i >= pattern.len() {
+        return (c == '[').then_some(start + 1);
+    }
+    (matched != negated).then_some(i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literals_and_wildcards() {
+        assert!(glob_match("user:1", "user:1"));
+        assert!(!glob_match("user:1", "user:12"));
+        assert!(glob_match("user:*", "user:123"));
+        assert!(glob_match("user:*", "user:"));
+        assert!(glob_match("*:profile", "user:7:profile"));
+        assert!(glob_match("u*r:*:p*", "user:7:profile"));
+        assert!(!glob_match("user:*:name", "user:7:profile"));
+        assert!(glob_match("user:?", "user:7"));
+        assert!(!glob_match("user:?", "user:77"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn test_character_classes() {
+        assert!(glob_match("h[ae]llo", "hello"));
+        assert!(glob_match("h[ae]llo", "hallo"));
+        assert!(!glob_match("h[ae]llo", "hillo"));
+        assert!(glob_match("item:[0-9]", "item:5"));
+        assert!(!glob_match("item:[0-9]", "item:x"));
+        assert!(glob_match("h[!e]llo", "hallo"));
+        assert!(!glob_match("h[^e]llo", "hello"));
+        assert!(glob_match("a[]]b", "a]b"));
+        assert!(glob_match("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(glob_match(r"price\*", "price*"));
+        assert!(!glob_match(r"price\*", "prices"));
+        assert!(glob_match(r"what\?", "what?"));
+    }
+}
@@ -0,0 +1,264 @@
+//! In-process publish/subscribe channels, reached through [`MiniCache::publish`],
+//! [`MiniCache::subscribe`] and [`MiniCache::psubscribe`].
+//!
+//! [`MiniCache::publish`]: crate::MiniCache::publish
+//! [`MiniCache::subscribe`]: crate::MiniCache::subscribe
+//! [`MiniCache::psubscribe`]: crate::MiniCache::psubscribe
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+
+use crate::pattern::glob_match;
+
+/// Messages buffered per channel or pattern before slow subscribers start missing them.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// A message delivered to a [`Subscription`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message<V> {
+    /// The channel the message was published on.
+    pub channel: Arc<str>,
+    /// The pattern that matched the channel, for pattern subscriptions.
+    pub pattern: Option<Arc<str>>,
+    /// The published value.
+    pub payload: V,
+}
+
+/// A stream of messages for one channel or pattern.
+///
+/// Each subscriber buffers up to 1024 messages. A subscriber that falls further behind
+/// skips the oldest ones instead of slowing down publishers; [`missed`](Self::missed)
+/// counts how many were skipped.
+pub struct Subscription<V> {
+    receiver: broadcast::Receiver<Message<V>>,
+    missed: u64,
+}
+
+impl<V: Clone> Subscription<V> {
+    /// Waits for the next message.
+    ///
+    /// Returns `None` once every handle to the cache has been dropped and all buffered
+    /// messages have been received.
+    pub async fn recv(&mut self) -> Option<Message<V>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(message) => return Some(message),
+                Err(RecvError::Lagged(skipped)) => self.missed += skipped,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Returns the next message if one is already buffered, without waiting.
+    pub fn try_recv(&mut self) -> Option<Message<V>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => return Some(message),
+                Err(TryRecvError::Lagged(skipped)) => self.missed += skipped,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Returns how many messages were skipped because this subscriber fell behind.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+impl<V> fmt::Debug for Subscription<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("buffered", &self.receiver.len())
+            .field("missed", &self.missed)
+            .finish()
+    }
+}
+
+/// The channel and pattern senders shared by every handle to a cache.
+///
+/// Senders are created on first subscription and dropped by `publish` once their last
+/// subscriber is gone.
+pub(crate) struct Broker<V> {
+    channels: Mutex<Senders<V>>,
+    patterns: Mutex<Senders<V>>,
+}
+
+/// Senders keyed by channel name or pattern.
+type Senders<V> = HashMap<Arc<str>, broadcast::Sender<Message<V>>>;
+
+impl<V> Default for Broker<V> {
+    fn default() -> Self {
+        Broker {
+            channels: Mutex::default(),
+            patterns: Mutex::default(),
+        }
+    }
+}
+
+impl<V: Clone> Broker<V> {
+    fn lock(senders: &Mutex<Senders<V>>) -> MutexGuard<'_, Senders<V>> {
+        senders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn subscribe(&self, channel: &str) -> Subscription<V> {
+        Self::join(&mut Self::lock(&self.channels), channel)
+    }
+
+    pub(crate) fn psubscribe(&self, pattern: &str) -> Subscription<V> {
+        Self::join(&mut Self::lock(&self.patterns), pattern)
+    }
+
+    fn join(senders: &mut Senders<V>, name: &str) -> Subscription<V> {
+        let receiver = match senders.get(name) {
+            Some(sender) => sender.subscribe(),
+            None => {
+                let (sender, receiver) = broadcast::channel(CHANNEL_CAPACITY);
+                senders.insert(name.into(), sender);
+                receiver
+            }
+        };
+        Subscription {
+            receiver,
+            missed: 0,
+        }
+    }
+
+    /// Sends `payload` to the subscribers of `channel` and of every matching pattern,
+    /// returning how many subscribers it reached.
+    pub(crate) fn publish(&self, channel: &str, payload: V) -> usize {
+        let channel: Arc<str> = channel.into();
+        let mut delivered = 0;
+        {
+            let mut channels = Self::lock(&self.channels);
+            if let Some(sender) = channels.get(&channel) {
+                let message = Message {
+                    channel: channel.clone(),
+                    pattern: None,
+                    payload: payload.clone(),
+                };
+                match sender.send(message) {
+                    Ok(receivers) => delivered += receivers,
+                    Err(_) => {
+                        channels.remove(&channel);
+                    }
+                }
+            }
+        }
+        Self::lock(&self.patterns).retain(|pattern, sender| {
+            if !glob_match(pattern, &channel) {
+                return sender.receiver_count() > 0;
+            }
+            let message = Message {
+                channel: channel.clone(),
+                pattern: Some(pattern.clone()),
+                payload: payload.clone(),
+            };
+            match sender.send(message) {
+                Ok(receivers) => {
+                    delivered += receivers;
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+        delivered
+    }
+
+    /// Returns the channels that currently have subscribers.
+    pub(crate) fn channels(&self) -> Vec<String> {
+        let mut channels = Self::lock(&self.channels);
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        channels.keys().map(|channel| channel.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_reaches_channel_and_pattern_subscribers() {
+        let broker = Broker::default();
+        let mut news = broker.subscribe("news");
+        let mut other = broker.subscribe("weather");
+        let mut all = broker.psubscribe("n*");
+
+        assert_eq!(broker.publish("news", 1), 2);
+        assert_eq!(broker.publish("nothing", 2), 1);
+
+        let message = news.try_recv().unwrap();
+        assert_eq!(
+            (&*message.channel, message.pattern, message.payload),
+            ("news", None, 1)
+        );
+        assert_eq!(news.try_recv(), None);
+        assert_eq!(other.try_recv(), None);
+
+        let payloads: Vec<_> = std::iter::from_fn(|| all.try_recv())
+            .map(|m| {
+                (
+                    m.channel.to_string(),
+                    m.pattern.as_deref().map(str::to_string),
+                    m.payload,
+                )
+            })
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![
+                ("news".to_string(), Some("n*".to_string()), 1),
+                ("nothing".to_string(), Some("n*".to_string()), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dropped_subscribers_are_forgotten() {
+        let broker = Broker::default();
+        let first = broker.subscribe("jobs");
+        let pattern = broker.psubscribe("*");
+        assert_eq!(broker.channels(), vec!["jobs".to_string()]);
+
+        drop(first);
+        drop(pattern);
+        assert_eq!(broker.publish("jobs", "work"), 0);
+        assert!(Broker::lock(&broker.channels).is_empty());
+        assert!(Broker::lock(&broker.patterns).is_empty());
+    }
+
+    #[test]
+    fn test_broker_survives_poisoned_lock() {
+        let broker = Arc::new(Broker::default());
+        let mut news = broker.subscribe("news");
+
+        let poisoner = broker.clone();
+        let _ = std::thread::spawn(move || {
+            let _channels = Broker::lock(&poisoner.channels);
+            panic!("publisher failed");
+        })
+        .join();
+
+        assert!(broker.channels.is_poisoned());
+        assert_eq!(broker.publish("news", 1), 1);
+        assert_eq!(news.try_recv().map(|m| m.payload), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscribers_skip_old_messages() {
+        let broker = Broker::default();
+        let mut slow = broker.subscribe("ticks");
+
+        for i in 0..CHANNEL_CAPACITY + 10 {
+            broker.publish("ticks", i);
+        }
+
+        assert_eq!(slow.recv().await.unwrap().payload, 10);
+        assert_eq!(slow.missed(), 10);
+    }
+}
@@ -0,0 +1,403 @@
+//! Rate limiting on top of `MiniCache`, with fixed-window, sliding-log and token-bucket
+//! algorithms.
+
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::builder::MiniCacheBuilder;
+use crate::clock::Clock;
+use crate::collections::Collection;
+use crate::core::MiniCache;
+
+/// Upper bound on how long idle state is kept, for buckets that never refill.
+const MAX_IDLE_TTL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// The algorithm a [`RateLimiter`] applies to each key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    /// Allows `limit` requests per `window`, counted from the first request of the
+    /// window. Cheap, but allows bursts of up to twice the limit around a window edge.
+    FixedWindow {
+        /// Requests allowed per window.
+        limit: u64,
+        /// Length of a window.
+        window: Duration,
+    },
+    /// Allows `limit` requests in any `window`-long period by remembering the time of
+    /// each allowed request. Exact, at the cost of one timestamp per allowed request.
+    SlidingLog {
+        /// Requests allowed per window.
+        limit: u64,
+        /// Length of the sliding window.
+        window: Duration,
+    },
+    /// Allows bursts of up to `capacity` requests, refilled at `refill_per_sec` tokens
+    /// per second.
+    TokenBucket {
+        /// Maximum number of tokens, and so the largest burst.
+        capacity: u64,
+        /// Tokens added per second.
+        refill_per_sec: f64,
+    },
+}
+
+impl Algorithm {
+    /// How long an idle key is kept. Once it is gone, the key starts over with its full
+    /// quota, which is what it would have had anyway.
+    fn idle_ttl(&self) -> Duration {
+        match *self {
+            Algorithm::FixedWindow { window, .. } | Algorithm::SlidingLog { window, .. } => window,
+            Algorithm::TokenBucket {
+                capacity,
+                refill_per_sec,
+            } => refill_time(capacity as f64, refill_per_sec).min(MAX_IDLE_TTL),
+        }
+    }
+
+    /// Records a request at `now` against `state`, updating it in place.
+    fn apply(&self, state: &mut State, now: Instant) -> Decision {
+        match *self {
+            Algorithm::FixedWindow { limit, window } => {
+                let (count, started) = match *state {
+                    State::Window { count, started }
+                        if now.saturating_duration_since(started) < window =>
+                    {
+                        (count, started)
+                    }
+                    _ => (0, now),
+                };
+                let allowed = count < limit;
+                let count = if allowed { count + 1 } else { count };
+                *state = State::Window { count, started };
+                Decision {
+                    allowed,
+                    remaining: limit - count,
+                    reset_after: (started + window).saturating_duration_since(now),
+                }
+            }
+            Algorithm::SlidingLog { limit, window } => {
+                if !matches!(state, State::Log(_)) {
+                    *state = State::Log(VecDeque::new());
+                }
+                let State::Log(log) = state else {
+                    unreachable!("the state was just made a log");
+                };
+                while log
+                    .front()
+                    .is_some_and(|&t| now.saturating_duration_since(t) >= window)
+                {
+                    log.pop_front();
+                }
+                let allowed = (log.len() as u64) < limit;
+                if allowed {
+                    log.push_back(now);
+                }
+                Decision {
+                    allowed,
+                    remaining: limit.saturating_sub(log.len() as u64),
+                    reset_after: log.back().map_or(Duration::ZERO, |&t| {
+                        (t + window).saturating_duration_since(now)
+                    }),
+                }
+            }
+            Algorithm::TokenBucket {
+                capacity,
+                refill_per_sec,
+            } => {
+                let capacity = capacity as f64;
+                let tokens = match *state {
+                    State::Tokens { tokens, updated } => {
+                        let elapsed = now.saturating_duration_since(updated).as_secs_f64();
+                        (tokens + elapsed * refill_per_sec).min(capacity)
+                    }
+                    _ => capacity,
+                };
+                let allowed = tokens >= 1.0;
+                let tokens = if allowed { tokens - 1.0 } else { tokens };
+                *state = State::Tokens {
+                    tokens,
+                    updated: now,
+                };
+                Decision {
+                    allowed,
+                    remaining: tokens.floor() as u64,
+                    reset_after: refill_time(capacity - tokens, refill_per_sec),
+                }
+            }
+        }
+    }
+}
+
+/// Time needed to refill `tokens` tokens, saturating for a zero or invalid rate.
+fn refill_time(tokens: f64, refill_per_sec: f64) -> Duration {
+    Duration::try_from_secs_f64(tokens / refill_per_sec).unwrap_or(Duration::MAX)
+}
+
+/// The per-key state kept in the limiter's cache. A key starts out `New` and gets the
+/// state of the limiter's algorithm on its first check.
+#[derive(Clone, Default)]
+enum State {
+    #[default]
+    New,
+    Window {
+        count: u64,
+        started: Instant,
+    },
+    Log(VecDeque<Instant>),
+    Tokens {
+        tokens: f64,
+        updated: Instant,
+    },
+}
+
+impl Collection for State {
+    fn is_empty(&self) -> bool {
+        matches!(self, State::New)
+    }
+}
+
+/// The outcome of [`RateLimiter::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    /// Whether the request is allowed. Denied requests do not use up quota.
+    pub allowed: bool,
+    /// How many more requests would be allowed right now.
+    pub remaining: u64,
+    /// How long until the key's full quota is available again.
+    pub reset_after: Duration,
+}
+
+/// A per-key rate limiter backed by a `MiniCache`.
+///
+/// Every [`check`](Self::check) updates the key's state atomically, so concurrent
+/// checks never let more requests through than the limit. State for keys that go idle
+/// expires through the cache's TTLs, so the limiter does not grow with the number of
+/// keys ever seen. Clones share the same state.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{Algorithm, RateLimiter};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let limiter = RateLimiter::new(Algorithm::FixedWindow {
+///         limit: 2,
+///         window: Duration::from_secs(60),
+///     });
+///
+///     assert!(limiter.check(&"alice").await.allowed);
+///     assert!(limiter.check(&"alice").await.allowed);
+///
+///     let decision = limiter.check(&"alice").await;
+///     assert!(!decision.allowed);
+///     assert_eq!(decision.remaining, 0);
+///
+///     // Other keys have their own quota
+///     assert!(limiter.check(&"bob").await.allowed);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter<K> {
+    cache: MiniCache<K, State>,
+    algorithm: Algorithm,
+}
+
+impl<K> RateLimiter<K>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Creates a rate limiter that applies `algorithm` to every key.
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self::from_builder(
+            MiniCache::builder(Self::cleanup_interval(&algorithm)),
+            algorithm,
+        )
+    }
+
+    /// Creates a rate limiter that reads the time from `clock`.
+    ///
+    /// Useful with a [`MockClock`](crate::MockClock) to test rate limits without
+    /// waiting.
+    pub fn with_clock<C>(algorithm: Algorithm, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        let builder = MiniCache::builder(Self::cleanup_interval(&algorithm)).clock(clock);
+        Self::from_builder(builder, algorithm)
+    }
+
+    fn from_builder(builder: MiniCacheBuilder<K, State>, algorithm: Algorithm) -> Self {
+        RateLimiter {
+            cache: builder.build(),
+            algorithm,
+        }
+    }
+
+    /// Idle keys are purged about once per idle TTL, but at least every minute.
+    fn cleanup_interval(algorithm: &Algorithm) -> Duration {
+        algorithm
+            .idle_ttl()
+            .clamp(Duration::from_millis(1), Duration::from_secs(60))
+    }
+
+    /// Returns the algorithm the limiter applies.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Records a request for `key` and decides whether it is allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to rate limit, such as a user or client address
+    pub async fn check(&self, key: &K) -> Decision {
+        let algorithm = self.algorithm;
+        let keeps_deadline = matches!(algorithm, Algorithm::FixedWindow { .. });
+        self.cache
+            .mutate_at(
+                key,
+                Some(Some(algorithm.idle_ttl())),
+                !keeps_deadline,
+                |state, now| algorithm.apply(state, now),
+            )
+            .await
+            .expect("a missing key is created")
+    }
+
+    /// Forgets the state of `key`, giving it its full quota again.
+    pub async fn reset(&self, key: &K) {
+        self.cache.remove(key).await
+    }
+
+    /// Returns the number of keys with state currently kept.
+    pub async fn len(&self) -> usize {
+        self.cache.len().await
+    }
+
+    /// Returns `true` if no key has state kept.
+    pub async fn is_empty(&self) -> bool {
+        self.cache.is_empty().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn limiter(algorithm: Algorithm) -> (RateLimiter<&'static str>, MockClock) {
+        let clock = MockClock::new();
+        (RateLimiter::with_clock(algorithm, clock.clone()), clock)
+    }
+
+    #[tokio::test]
+    async fn test_fixed_window() {
+        let (limiter, clock) = limiter(Algorithm::FixedWindow {
+            limit: 3,
+            window: Duration::from_secs(10),
+        });
+
+        for remaining in [2, 1, 0] {
+            let decision = limiter.check(&"alice").await;
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+            assert_eq!(decision.reset_after, Duration::from_secs(10));
+        }
+
+        clock.advance(Duration::from_secs(4));
+        let denied = limiter.check(&"alice").await;
+        assert!(!denied.allowed);
+        assert_eq!(denied.reset_after, Duration::from_secs(6));
+        assert!(limiter.check(&"bob").await.allowed);
+
+        clock.advance(Duration::from_secs(6));
+        let decision = limiter.check(&"alice").await;
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 2);
+    }
+
+    #[tokio::test]
+    async fn test_sliding_log() {
+        let (limiter, clock) = limiter(Algorithm::SlidingLog {
+            limit: 2,
+            window: Duration::from_secs(10),
+        });
+
+        assert!(limiter.check(&"alice").await.allowed);
+        clock.advance(Duration::from_secs(6));
+        assert!(limiter.check(&"alice").await.allowed);
+        assert!(!limiter.check(&"alice").await.allowed);
+
+        // The first request leaves the window, the second is still in it
+        clock.advance(Duration::from_secs(4));
+        let decision = limiter.check(&"alice").await;
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.reset_after, Duration::from_secs(10));
+        assert!(!limiter.check(&"alice").await.allowed);
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket() {
+        let (limiter, clock) = limiter(Algorithm::TokenBucket {
+            capacity: 2,
+            refill_per_sec: 1.0,
+        });
+
+        assert!(limiter.check(&"alice").await.allowed);
+        let decision = limiter.check(&"alice").await;
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.reset_after, Duration::from_secs(2));
+        assert!(!limiter.check(&"alice").await.allowed);
+
+        clock.advance(Duration::from_millis(1500));
+        let decision = limiter.check(&"alice").await;
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert!(!limiter.check(&"alice").await.allowed);
+    }
+
+    #[tokio::test]
+    async fn test_idle_keys_expire() {
+        let (limiter, clock) = limiter(Algorithm::SlidingLog {
+            limit: 1,
+            window: Duration::from_secs(10),
+        });
+
+        limiter.check(&"alice").await;
+        limiter.check(&"bob").await;
+        assert_eq!(limiter.len().await, 2);
+
+        clock.advance(Duration::from_secs(10));
+        assert!(limiter.is_empty().await);
+
+        limiter.check(&"alice").await;
+        limiter.reset(&"alice").await;
+        assert!(limiter.check(&"alice").await.allowed);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_checks_respect_the_limit() {
+        let limiter = RateLimiter::new(Algorithm::FixedWindow {
+            limit: 25,
+            window: Duration::from_secs(60),
+        });
+
+        let tasks: Vec<_> = (0..100)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.check(&"shared").await.allowed })
+            })
+            .collect();
+        let mut allowed = 0;
+        for task in tasks {
+            allowed += task.await.unwrap() as u32;
+        }
+
+        assert_eq!(allowed, 25);
+    }
+}
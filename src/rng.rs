@@ -0,0 +1,88 @@
+//! Small thread-safe pseudo-random number generator used for probabilistic expiry.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Increment of the SplitMix64 sequence.
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A SplitMix64 generator whose state is a single atomic counter.
+///
+/// It is not cryptographically secure; it only needs to be cheap, lock-free and
+/// reproducible from a seed.
+pub(crate) struct Rng {
+    state: AtomicU64,
+}
+
+impl Rng {
+    /// Creates a generator that yields the same sequence for the same seed.
+    pub(crate) fn with_seed(seed: u64) -> Self {
+        Rng {
+            state: AtomicU64::new(seed),
+        }
+    }
+
+    /// Creates a generator seeded from the process's random hashing keys.
+    pub(crate) fn from_entropy() -> Self {
+        Self::with_seed(entropy())
+    }
+
+    pub(crate) fn next_u64(&self) -> u64 {
+        let state = self.state.fetch_add(GOLDEN_GAMMA, Ordering::Relaxed);
+        mix(state.wrapping_add(GOLDEN_GAMMA))
+    }
+
+    /// Returns a uniformly distributed value in `[0, 1)`.
+    pub(crate) fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Returns a uniformly distributed value in `[-1, 1)`.
+    pub(crate) fn next_signed_f64(&self) -> f64 {
+        self.next_f64() * 2.0 - 1.0
+    }
+}
+
+/// A random seed taken from the process's random hashing keys.
+pub(crate) fn entropy() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Advances a SplitMix64 `state` owned by the caller and returns the next output,
+/// for single-threaded users that do not need an atomic [`Rng`].
+pub(crate) fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(GOLDEN_GAMMA);
+    mix(*state)
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let a = Rng::with_seed(42);
+        let b = Rng::with_seed(42);
+
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_next_f64_is_in_unit_interval() {
+        let rng = Rng::with_seed(7);
+
+        for _ in 0..1000 {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
+}
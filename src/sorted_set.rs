@@ -0,0 +1,591 @@
+//! A sorted-set value type with Redis-style `Z*` operations on the cache.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::collections::Collection;
+use crate::core::MiniCache;
+use crate::rng;
+
+/// A score ordered with [`f64::total_cmp`], so members can be ordered by it.
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A node of the treap that orders the members of a [`SortedSet`] by score, then by
+/// member.
+///
+/// Nodes are kept in search-tree order by `(score, member)` and in heap order by a
+/// random `priority`, which keeps the tree balanced in expectation. `size` counts the
+/// nodes of the subtree, which is what lets ranks be found in `O(log n)`.
+#[derive(Debug, Clone)]
+struct Node<M> {
+    score: Score,
+    member: M,
+    priority: u64,
+    size: usize,
+    left: Tree<M>,
+    right: Tree<M>,
+}
+
+type Tree<M> = Option<Box<Node<M>>>;
+
+fn size<M>(tree: &Tree<M>) -> usize {
+    tree.as_ref().map_or(0, |node| node.size)
+}
+
+impl<M> Node<M> {
+    fn resize(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+/// Splits `tree` into the nodes `before` accepts and the rest. `before` must accept a
+/// prefix of the order.
+fn split<M>(tree: Tree<M>, before: &impl Fn(Score, &M) -> bool) -> (Tree<M>, Tree<M>) {
+    let Some(mut node) = tree else {
+        return (None, None);
+    };
+    if before(node.score, &node.member) {
+        let (left, right) = split(node.right.take(), before);
+        node.right = left;
+        node.resize();
+        (Some(node), right)
+    } else {
+        let (left, right) = split(node.left.take(), before);
+        node.left = right;
+        node.resize();
+        (left, Some(node))
+    }
+}
+
+/// Joins two trees where every node of `left` orders before every node of `right`.
+fn merge<M>(left: Tree<M>, right: Tree<M>) -> Tree<M> {
+    match (left, right) {
+        (None, tree) | (tree, None) => tree,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                left.resize();
+                Some(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                right.resize();
+                Some(right)
+            }
+        }
+    }
+}
+
+/// Counts the nodes `before` accepts, which must be a prefix of the order.
+fn count<M>(mut tree: &Tree<M>, before: impl Fn(Score, &M) -> bool) -> usize {
+    let mut count = 0;
+    while let Some(node) = tree {
+        if before(node.score, &node.member) {
+            count += size(&node.left) + 1;
+            tree = &node.right;
+        } else {
+            tree = &node.left;
+        }
+    }
+    count
+}
+
+/// Appends up to `take` members to `out`, starting at rank `skip` within `tree`.
+fn collect<M: Clone>(tree: &Tree<M>, skip: usize, take: &mut usize, out: &mut Vec<(M, f64)>) {
+    let Some(node) = tree else {
+        return;
+    };
+    let left = size(&node.left);
+    if skip < left {
+        collect(&node.left, skip, take, out);
+    }
+    if *take == 0 {
+        return;
+    }
+    if skip <= left {
+        out.push((node.member.clone(), node.score.0));
+        *take -= 1;
+    }
+    collect(&node.right, skip.saturating_sub(left + 1), take, out);
+}
+
+/// An in-order walk over a treap that can be taken from both ends.
+struct Walk<'a, M> {
+    front: Vec<&'a Node<M>>,
+    back: Vec<&'a Node<M>>,
+    remaining: usize,
+}
+
+impl<'a, M> Walk<'a, M> {
+    fn new(tree: &'a Tree<M>) -> Self {
+        let mut walk = Walk {
+            front: Vec::new(),
+            back: Vec::new(),
+            remaining: size(tree),
+        };
+        walk.descend_left(tree);
+        walk.descend_right(tree);
+        walk
+    }
+
+    fn descend_left(&mut self, mut tree: &'a Tree<M>) {
+        while let Some(node) = tree {
+            self.front.push(node);
+            tree = &node.left;
+        }
+    }
+
+    fn descend_right(&mut self, mut tree: &'a Tree<M>) {
+        while let Some(node) = tree {
+            self.back.push(node);
+            tree = &node.right;
+        }
+    }
+}
+
+impl<'a, M> Iterator for Walk<'a, M> {
+    type Item = (&'a M, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.front.pop()?;
+        self.descend_left(&node.right);
+        self.remaining -= 1;
+        Some((&node.member, node.score.0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<M> DoubleEndedIterator for Walk<'_, M> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.back.pop()?;
+        self.descend_right(&node.left);
+        self.remaining -= 1;
+        Some((&node.member, node.score.0))
+    }
+}
+
+/// A set of unique members ordered by score, like a Redis sorted set.
+///
+/// Members with equal scores are ordered by the members themselves. Adding, removing
+/// and ranking a member, and finding where a rank or score range starts, take
+/// `O(log n)` expected time; the members are kept in a treap whose nodes count their
+/// subtrees, much as Redis keeps span counts in its skip lists.
+///
+/// Store it as the value of a `MiniCache` to use the `z*` operations, which update the
+/// set in place under the cache lock.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::SortedSet;
+///
+/// let mut scores = SortedSet::new();
+/// scores.insert("alice", 30.0);
+/// scores.insert("bob", 10.0);
+/// scores.insert("carol", 20.0);
+///
+/// assert_eq!(scores.rank(&"alice"), Some(2));
+/// assert_eq!(scores.range_by_score(15.0, 30.0), vec![("carol", 20.0), ("alice", 30.0)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SortedSet<M> {
+    scores: HashMap<M, f64>,
+    tree: Tree<M>,
+    /// State of the generator for node priorities.
+    seed: u64,
+}
+
+impl<M> Default for SortedSet<M> {
+    fn default() -> Self {
+        SortedSet {
+            scores: HashMap::new(),
+            tree: None,
+            seed: rng::entropy(),
+        }
+    }
+}
+
+impl<M> PartialEq for SortedSet<M>
+where
+    M: Hash + Eq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl<M> SortedSet<M>
+where
+    M: Ord + Hash + Clone,
+{
+    /// Creates an empty sorted set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of members.
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Returns `true` if the set has no members.
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Adds `member` with `score`, or updates its score. Returns `true` if the member
+    /// is new.
+    pub fn insert(&mut self, member: M, score: f64) -> bool {
+        let previous = self.detach(&member);
+        let score = Score(score);
+        let (below, above) = split(self.tree.take(), &|s, m| (s, m) < (score, &member));
+        let node = Node {
+            score,
+            member: member.clone(),
+            priority: rng::next(&mut self.seed),
+            size: 1,
+            left: None,
+            right: None,
+        };
+        self.tree = merge(merge(below, Some(Box::new(node))), above);
+        self.scores.insert(member, score.0);
+        previous.is_none()
+    }
+
+    /// Adds `delta` to the score of `member`, adding it with a score of `delta` if it
+    /// is missing, and returns the new score.
+    pub fn increment(&mut self, member: M, delta: f64) -> f64 {
+        let score = self.score(&member).unwrap_or(0.0) + delta;
+        self.insert(member, score);
+        score
+    }
+
+    /// Removes `member`, returning `true` if it was present.
+    pub fn remove(&mut self, member: &M) -> bool {
+        self.detach(member).is_some()
+    }
+
+    /// Removes `member` from both indexes and returns its score.
+    fn detach(&mut self, member: &M) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        let key = (Score(score), member);
+        let (below, rest) = split(self.tree.take(), &|s, m| (s, m) < key);
+        let (_, above) = split(rest, &|s, m| (s, m) <= key);
+        self.tree = merge(below, above);
+        Some(score)
+    }
+
+    /// Returns the score of `member`.
+    pub fn score(&self, member: &M) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Returns the zero-based rank of `member` in ascending score order.
+    pub fn rank(&self, member: &M) -> Option<usize> {
+        let key = (Score(self.score(member)?), member);
+        Some(count(&self.tree, |s, m| (s, m) < key))
+    }
+
+    /// Iterates over the members and scores in ascending score order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&M, f64)> {
+        Walk::new(&self.tree)
+    }
+
+    /// Returns the members ranked from `start` to `stop`, both inclusive, with their
+    /// scores.
+    ///
+    /// Negative ranks count from the highest score, so `0, -1` returns every member.
+    /// Ranks past either end are clamped.
+    pub fn range_by_rank(&self, start: isize, stop: isize) -> Vec<(M, f64)> {
+        let len = self.len() as isize;
+        let resolve = |rank: isize| if rank < 0 { len + rank } else { rank };
+        let (start, stop) = (resolve(start).max(0), resolve(stop).min(len - 1));
+        if start > stop {
+            return Vec::new();
+        }
+        let mut take = (stop - start + 1) as usize;
+        let mut members = Vec::with_capacity(take);
+        collect(&self.tree, start as usize, &mut take, &mut members);
+        members
+    }
+
+    /// Returns the members whose scores lie between `min` and `max`, both inclusive,
+    /// in ascending score order.
+    pub fn range_by_score(&self, min: f64, max: f64) -> Vec<(M, f64)> {
+        let (min, max) = (Score(min), Score(max));
+        if min > max {
+            return Vec::new();
+        }
+        let start = count(&self.tree, |s, _| s < min);
+        let mut take = count(&self.tree, |s, _| s <= max) - start;
+        let mut members = Vec::with_capacity(take);
+        collect(&self.tree, start, &mut take, &mut members);
+        members
+    }
+}
+
+impl<M> Collection for SortedSet<M> {
+    fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+}
+
+impl<M> FromIterator<(M, f64)> for SortedSet<M>
+where
+    M: Ord + Hash + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (M, f64)>>(iter: I) -> Self {
+        let mut set = SortedSet::new();
+        for (member, score) in iter {
+            set.insert(member, score);
+        }
+        set
+    }
+}
+
+impl<K, M> MiniCache<K, SortedSet<M>>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    M: Ord + Hash + Clone + Send + Sync + 'static,
+{
+    /// Adds `member` with `score` to the sorted set at `key`, or updates its score.
+    /// Returns `true` if the member is new (`ZADD`).
+    ///
+    /// A missing key is created with `ttl`; an existing key keeps its TTL. Like the
+    /// other collection operations, this does not reach a backing store.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minicache::{MiniCache, SortedSet};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cache: MiniCache<&str, SortedSet<&str>> = MiniCache::new(Duration::from_secs(60));
+    ///
+    ///     cache.zadd(&"leaderboard", "alice", 120.0, None).await;
+    ///     cache.zadd(&"leaderboard", "bob", 90.0, None).await;
+    ///     cache.zincrby(&"leaderboard", "bob", 50.0, None).await;
+    ///
+    ///     // Highest score first
+    ///     let top = cache.zrange(&"leaderboard", -1, -1).await;
+    ///     assert_eq!(top, vec![("bob", 140.0)]);
+    ///     assert_eq!(cache.zrank(&"leaderboard", &"alice").await, Some(0));
+    /// }
+    /// ```
+    pub async fn zadd(&self, key: &K, member: M, score: f64, ttl: Option<Duration>) -> bool {
+        self.mutate(key, Some(ttl), |set| set.insert(member, score))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Adds `delta` to the score of `member` in the sorted set at `key` and returns
+    /// the new score (`ZINCRBY`).
+    ///
+    /// A missing member starts at zero. A missing key is created with `ttl`; an
+    /// existing key keeps its TTL.
+    pub async fn zincrby(&self, key: &K, member: M, delta: f64, ttl: Option<Duration>) -> f64 {
+        self.mutate(key, Some(ttl), |set| set.increment(member, delta))
+            .await
+            .unwrap_or(delta)
+    }
+
+    /// Removes `member` from the sorted set at `key`, returning `true` if it was there
+    /// (`ZREM`).
+    pub async fn zrem(&self, key: &K, member: &M) -> bool {
+        self.mutate(key, None, |set| set.remove(member))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Returns the members of the sorted set at `key` ranked from `start` to `stop`,
+    /// both inclusive, in ascending score order (`ZRANGE`).
+    ///
+    /// Negative ranks count from the highest score.
+    pub async fn zrange(&self, key: &K, start: isize, stop: isize) -> Vec<(M, f64)> {
+        self.inspect(key, |set| set.range_by_rank(start, stop))
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Returns the members of the sorted set at `key` with scores between `min` and
+    /// `max`, both inclusive (`ZRANGEBYSCORE`).
+    pub async fn zrangebyscore(&self, key: &K, min: f64, max: f64) -> Vec<(M, f64)> {
+        self.inspect(key, |set| set.range_by_score(min, max))
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Returns the zero-based rank of `member` in ascending score order (`ZRANK`).
+    pub async fn zrank(&self, key: &K, member: &M) -> Option<usize> {
+        self.inspect(key, |set| set.rank(member)).await.flatten()
+    }
+
+    /// Returns the score of `member` in the sorted set at `key` (`ZSCORE`).
+    pub async fn zscore(&self, key: &K, member: &M) -> Option<f64> {
+        self.inspect(key, |set| set.score(member)).await.flatten()
+    }
+
+    /// Returns the number of members of the sorted set at `key` (`ZCARD`).
+    pub async fn zcard(&self, key: &K) -> usize {
+        self.inspect(key, SortedSet::len).await.unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_sorted_set_ordering() {
+        let mut set: SortedSet<&str> = [("c", 2.0), ("a", 2.0), ("b", 1.0)].into_iter().collect();
+
+        let members: Vec<_> = set.iter().map(|(m, _)| *m).collect();
+        assert_eq!(members, vec!["b", "a", "c"]);
+        assert_eq!(set.rank(&"c"), Some(2));
+        assert_eq!(set.rank(&"missing"), None);
+
+        assert!(!set.insert("b", 3.0));
+        assert_eq!(set.rank(&"b"), Some(2));
+        assert_eq!(set.increment("d", -1.0), -1.0);
+        assert_eq!(set.range_by_rank(0, 0), vec![("d", -1.0)]);
+
+        assert!(set.remove(&"a"));
+        assert!(!set.remove(&"a"));
+        assert_eq!(set.len(), 3);
+        assert_eq!(size(&set.tree), 3);
+    }
+
+    #[test]
+    fn test_sorted_set_ranges() {
+        let set: SortedSet<u32> = (1..=10).map(|i| (i, i as f64 * 10.0)).collect();
+
+        assert_eq!(set.range_by_rank(0, 1), vec![(1, 10.0), (2, 20.0)]);
+        assert_eq!(set.range_by_rank(-2, -1), vec![(9, 90.0), (10, 100.0)]);
+        assert_eq!(
+            set.range_by_rank(6, 8),
+            vec![(7, 70.0), (8, 80.0), (9, 90.0)]
+        );
+        assert_eq!(set.range_by_rank(-100, 0), vec![(1, 10.0)]);
+        assert_eq!(set.range_by_rank(8, 100).len(), 2);
+        assert!(set.range_by_rank(5, 2).is_empty());
+
+        assert_eq!(set.range_by_score(25.0, 40.0), vec![(3, 30.0), (4, 40.0)]);
+        assert!(set.range_by_score(40.0, 25.0).is_empty());
+        assert_eq!(
+            set.range_by_score(f64::NEG_INFINITY, f64::INFINITY).len(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_sorted_set_ranks_match_sorted_order() {
+        let mut set = SortedSet::new();
+        let mut state = 7;
+        for _ in 0..2000 {
+            let member = rng::next(&mut state) % 500;
+            if rng::next(&mut state).is_multiple_of(4) {
+                set.remove(&member);
+            } else {
+                set.insert(member, (rng::next(&mut state) % 50) as f64);
+            }
+        }
+
+        let mut expected: Vec<_> = set.scores.iter().map(|(m, s)| (*m, *s)).collect();
+        expected.sort_by(|a, b| Score(a.1).cmp(&Score(b.1)).then(a.0.cmp(&b.0)));
+        assert_eq!(size(&set.tree), expected.len());
+        assert_eq!(set.range_by_rank(0, -1), expected);
+        let reversed: Vec<_> = set.iter().rev().map(|(m, s)| (*m, s)).collect();
+        assert!(reversed.iter().rev().eq(expected.iter()));
+        for (rank, (member, _)) in expected.iter().enumerate() {
+            assert_eq!(set.rank(member), Some(rank));
+        }
+        assert_eq!(set.range_by_rank(10, 19), expected[10..20]);
+        let in_range: Vec<_> = expected
+            .iter()
+            .copied()
+            .filter(|(_, s)| (10.0..=20.0).contains(s))
+            .collect();
+        assert_eq!(set.range_by_score(10.0, 20.0), in_range);
+    }
+
+    #[tokio::test]
+    async fn test_cache_sorted_set_operations() {
+        let cache: MiniCache<&str, SortedSet<&str>> = MiniCache::new(Duration::from_secs(1));
+
+        assert!(cache.zadd(&"board", "alice", 10.0, None).await);
+        assert!(!cache.zadd(&"board", "alice", 15.0, None).await);
+        assert_eq!(cache.zincrby(&"board", "bob", 20.0, None).await, 20.0);
+        assert_eq!(cache.zincrby(&"board", "bob", 5.0, None).await, 25.0);
+
+        assert_eq!(
+            cache.zrange(&"board", 0, -1).await,
+            vec![("alice", 15.0), ("bob", 25.0)]
+        );
+        assert_eq!(
+            cache.zrangebyscore(&"board", 20.0, 30.0).await,
+            vec![("bob", 25.0)]
+        );
+        assert_eq!(cache.zrank(&"board", &"bob").await, Some(1));
+        assert_eq!(cache.zscore(&"board", &"alice").await, Some(15.0));
+        assert_eq!(cache.zcard(&"board").await, 2);
+
+        assert!(cache.zrem(&"board", &"alice").await);
+        assert!(cache.zrem(&"board", &"bob").await);
+        assert!(!cache.contains(&"board").await);
+        assert_eq!(cache.zrank(&"board", &"bob").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_sorted_set_follows_ttl_and_eviction() {
+        let clock = MockClock::new();
+        let cache: MiniCache<&str, SortedSet<&str>> = MiniCache::builder(Duration::from_secs(1))
+            .clock(clock.clone())
+            .capacity(1)
+            .build();
+
+        cache
+            .zadd(&"daily", "alice", 1.0, Some(Duration::from_secs(10)))
+            .await;
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.zcard(&"daily").await, 0);
+
+        cache.zadd(&"a", "alice", 1.0, None).await;
+        cache.zadd(&"b", "bob", 1.0, None).await;
+        assert_eq!(cache.zcard(&"a").await, 0);
+        assert_eq!(cache.zcard(&"b").await, 1);
+    }
+}
@@ -0,0 +1,647 @@
+//! Pluggable backing stores for write-through and write-behind caching.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Mutex as AsyncMutex, Notify};
+use tokio::time::{interval, sleep};
+
+use crate::keylock::{KeyGuard, KeyLocks};
+
+/// First pause of `flush` between passes that leave failed writes queued; it doubles
+/// after every such pass up to [`MAX_FLUSH_BACKOFF`].
+const FLUSH_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_FLUSH_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Error type returned by [`Store`] implementations.
+pub type StoreError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A persistent system of record that a `MiniCache` reads from and writes to.
+///
+/// Attach a store with [`MiniCacheBuilder::write_through`] or
+/// [`MiniCacheBuilder::write_behind`]. A `get` that misses the cache calls
+/// [`Store::load`] and caches the result; `set` and `remove` are forwarded to
+/// [`Store::store`] and [`Store::delete`] either immediately or in batches.
+///
+/// [`MiniCacheBuilder::write_through`]: crate::MiniCacheBuilder::write_through
+/// [`MiniCacheBuilder::write_behind`]: crate::MiniCacheBuilder::write_behind
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{Store, StoreError};
+/// use std::collections::HashMap;
+/// use std::sync::Mutex;
+///
+/// struct Database {
+///     rows: Mutex<HashMap<u64, String>>,
+/// }
+///
+/// #[async_trait::async_trait]
+/// impl Store<u64, String> for Database {
+///     async fn load(&self, key: &u64) -> Result<Option<String>, StoreError> {
+///         Ok(self.rows.lock().unwrap().get(key).cloned())
+///     }
+///
+///     async fn store(&self, key: &u64, value: &String) -> Result<(), StoreError> {
+///         self.rows.lock().unwrap().insert(*key, value.clone());
+///         Ok(())
+///     }
+///
+///     async fn delete(&self, key: &u64) -> Result<(), StoreError> {
+///         self.rows.lock().unwrap().remove(key);
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Store<K, V>: Send + Sync
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+{
+    /// Loads the value for `key`, or `None` if the store has no such key.
+    async fn load(&self, key: &K) -> Result<Option<V>, StoreError>;
+
+    /// Writes `value` for `key`.
+    async fn store(&self, key: &K, value: &V) -> Result<(), StoreError>;
+
+    /// Deletes `key`. Deleting a missing key is not an error.
+    async fn delete(&self, key: &K) -> Result<(), StoreError>;
+
+    /// Writes several entries at once. Used by write-behind flushes.
+    ///
+    /// The default implementation calls [`Store::store`] for each entry; override it
+    /// when the store supports a cheaper bulk write.
+    async fn store_batch(&self, entries: &[(K, V)]) -> Result<(), StoreError> {
+        for (key, value) in entries {
+            self.store(key, value).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A `Store` that keeps everything in a shared in-memory map.
+///
+/// Clones share the same map, which makes it convenient for tests and examples.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::{MemoryStore, MiniCache};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let store = MemoryStore::new();
+///     let cache = MiniCache::builder(Duration::from_secs(60))
+///         .write_through(store.clone())
+///         .build();
+///
+///     cache.set("key1", "value1", None).await;
+///     assert_eq!(store.get(&"key1"), Some("value1"));
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore<K, V> {
+    map: Arc<Mutex<HashMap<K, V>>>,
+}
+
+impl<K, V> MemoryStore<K, V>
+where
+    K: Hash + Eq,
+    V: Clone,
+{
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        MemoryStore {
+            map: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the value stored for `key`.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.lock().get(key).cloned()
+    }
+
+    /// Returns the number of stored entries.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the store holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<K, V>> {
+        self.map.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl<K, V> Store<K, V> for MemoryStore<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    async fn load(&self, key: &K) -> Result<Option<V>, StoreError> {
+        Ok(self.get(key))
+    }
+
+    async fn store(&self, key: &K, value: &V) -> Result<(), StoreError> {
+        self.lock().insert(key.clone(), value.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, key: &K) -> Result<(), StoreError> {
+        self.lock().remove(key);
+        Ok(())
+    }
+}
+
+/// Settings for write-behind mode.
+///
+/// Writes are coalesced per key, so only the latest `set` or `remove` of a key is sent
+/// to the store. Pending writes are flushed every `flush_interval` in batches of at most
+/// `batch_size`; a write that fails is retried on later flushes up to `max_retries` times
+/// before it is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use minicache::WriteBehind;
+/// use std::time::Duration;
+///
+/// let config = WriteBehind::new(Duration::from_millis(500))
+///     .batch_size(250)
+///     .max_retries(5);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WriteBehind {
+    flush_interval: Duration,
+    batch_size: usize,
+    max_retries: u32,
+}
+
+impl WriteBehind {
+    /// Creates a write-behind configuration that flushes every `flush_interval`,
+    /// in batches of 100 with up to 3 retries.
+    pub fn new(flush_interval: Duration) -> Self {
+        WriteBehind {
+            flush_interval,
+            batch_size: 100,
+            max_retries: 3,
+        }
+    }
+
+    /// Sets the maximum number of entries passed to a single [`Store::store_batch`] call.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets how many times a failed write is retried before it is dropped.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+/// A write waiting to be flushed to the store.
+#[derive(Clone)]
+enum Op<V> {
+    Store(V),
+    Delete,
+}
+
+struct Pending<V> {
+    op: Op<V>,
+    attempts: u32,
+}
+
+/// Writes not yet acknowledged by the store.
+///
+/// A flush moves writes from `pending` to `in_flight` and only forgets them once the
+/// store has acknowledged them, so a `load` in between still sees the latest write
+/// instead of reading the store's old value.
+struct Queued<K, V> {
+    pending: HashMap<K, Pending<V>>,
+    in_flight: HashMap<K, Op<V>>,
+}
+
+/// Coalescing queue of pending writes used in write-behind mode.
+struct WriteQueue<K, V> {
+    queued: Mutex<Queued<K, V>>,
+    /// Held by a flush for its whole pass, so the store receives writes in order.
+    flushing: AsyncMutex<()>,
+    config: WriteBehind,
+    shutdown: Notify,
+}
+
+impl<K, V> WriteQueue<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queued<K, V>> {
+        self.queued.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn enqueue(&self, key: K, op: Op<V>) {
+        self.lock().pending.insert(key, Pending { op, attempts: 0 });
+    }
+
+    /// Returns the latest write for `key` that the store has not acknowledged yet.
+    fn pending_op(&self, key: &K) -> Option<Op<V>> {
+        let queued = self.lock();
+        match queued.pending.get(key) {
+            Some(item) => Some(item.op.clone()),
+            None => queued.in_flight.get(key).cloned(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lock().pending.is_empty()
+    }
+
+    /// Forgets in-flight writes the store has acknowledged.
+    fn acknowledge<'a>(&self, keys: impl IntoIterator<Item = &'a K>) {
+        let mut queued = self.lock();
+        for key in keys {
+            queued.in_flight.remove(key);
+        }
+    }
+
+    /// Puts back writes that failed, unless a newer write for the key arrived meanwhile.
+    fn requeue(&self, failed: Vec<(K, Pending<V>)>) -> bool {
+        let mut dropped = false;
+        let mut queued = self.lock();
+        for (key, mut item) in failed {
+            queued.in_flight.remove(&key);
+            item.attempts += 1;
+            if item.attempts > self.config.max_retries {
+                dropped = true;
+            } else {
+                queued.pending.entry(key).or_insert(item);
+            }
+        }
+        dropped
+    }
+
+    /// Sends every write pending at the start of the call to the store once.
+    async fn flush_once(&self, store: &dyn Store<K, V>) -> Result<(), StoreError> {
+        let _flushing = self.flushing.lock().await;
+        let drained: Vec<(K, Pending<V>)> = {
+            let mut queued = self.lock();
+            let drained: Vec<_> = queued.pending.drain().collect();
+            for (key, item) in &drained {
+                queued.in_flight.insert(key.clone(), item.op.clone());
+            }
+            drained
+        };
+        let mut writes = Vec::new();
+        let mut deletes = Vec::new();
+        for (key, item) in drained {
+            match item.op {
+                Op::Store(_) => writes.push((key, item)),
+                Op::Delete => deletes.push((key, item)),
+            }
+        }
+
+        let mut last_error = None;
+        let mut failed = Vec::new();
+        while !writes.is_empty() {
+            let rest = writes.split_off(writes.len().min(self.config.batch_size));
+            let batch: Vec<(K, V)> = writes
+                .iter()
+                .filter_map(|(key, item)| match &item.op {
+                    Op::Store(value) => Some((key.clone(), value.clone())),
+                    Op::Delete => None,
+                })
+                .collect();
+            match store.store_batch(&batch).await {
+                Ok(()) => self.acknowledge(batch.iter().map(|(key, _)| key)),
+                Err(e) => {
+                    last_error = Some(e);
+                    failed.append(&mut writes);
+                }
+            }
+            writes = rest;
+        }
+        for (key, item) in deletes {
+            match store.delete(&key).await {
+                Ok(()) => self.acknowledge([&key]),
+                Err(e) => {
+                    last_error = Some(e);
+                    failed.push((key, item));
+                }
+            }
+        }
+
+        let dropped = self.requeue(failed);
+        match last_error {
+            Some(e) if dropped => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Flushes until nothing is pending, retrying failed writes up to the retry limit.
+    ///
+    /// Passes that leave writes queued are spaced out with an exponential backoff, so
+    /// a failing store is not hammered with retries.
+    async fn flush_all(&self, store: &dyn Store<K, V>) -> Result<(), StoreError> {
+        let mut result = Ok(());
+        let mut backoff = FLUSH_BACKOFF;
+        while !self.is_empty() {
+            if let Err(e) = self.flush_once(store).await {
+                result = Err(e);
+            }
+            if !self.is_empty() {
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_FLUSH_BACKOFF);
+            }
+        }
+        result
+    }
+}
+
+/// The store attached to a `MiniCache`, plus the write-behind queue when enabled.
+pub(crate) struct Backing<K, V> {
+    store: Arc<dyn Store<K, V>>,
+    queue: Option<Arc<WriteQueue<K, V>>>,
+    locks: KeyLocks<K>,
+}
+
+impl<K, V> Backing<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub(crate) fn write_through(store: Arc<dyn Store<K, V>>) -> Self {
+        Backing {
+            store,
+            queue: None,
+            locks: KeyLocks::default(),
+        }
+    }
+
+    pub(crate) fn write_behind(store: Arc<dyn Store<K, V>>, config: WriteBehind) -> Self {
+        let queue = Arc::new(WriteQueue {
+            queued: Mutex::new(Queued {
+                pending: HashMap::new(),
+                in_flight: HashMap::new(),
+            }),
+            flushing: AsyncMutex::new(()),
+            config,
+            shutdown: Notify::new(),
+        });
+        Backing {
+            store,
+            queue: Some(queue),
+            locks: KeyLocks::default(),
+        }
+    }
+
+    /// Spawns the periodic flush task when running in write-behind mode.
+    pub(crate) fn spawn_flusher(&self) {
+        let Some(queue) = self.queue.clone() else {
+            return;
+        };
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            let mut ticker = interval(queue.config.flush_interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        let _ = queue.flush_once(store.as_ref()).await;
+                    }
+                    _ = queue.shutdown.notified() => break,
+                }
+            }
+        });
+    }
+
+    /// Loads `key`, serving a write-behind value the store has not acknowledged yet
+    /// before asking the store.
+    pub(crate) async fn load(&self, key: &K) -> Result<Option<V>, StoreError> {
+        if let Some(queue) = &self.queue {
+            match queue.pending_op(key) {
+                Some(Op::Store(value)) => return Ok(Some(value)),
+                Some(Op::Delete) => return Ok(None),
+                None => {}
+            }
+        }
+        self.store.load(key).await
+    }
+
+    /// Waits until no other cache operation is writing `key` through to the store.
+    ///
+    /// Holding the guard across the cache update and the store write keeps the two in
+    /// the same order for every key.
+    pub(crate) async fn guard(&self, key: &K) -> KeyGuard<'_, K> {
+        self.locks.lock(key).await
+    }
+
+    pub(crate) async fn store(&self, key: &K, value: &V) -> Result<(), StoreError> {
+        match &self.queue {
+            Some(queue) => {
+                queue.enqueue(key.clone(), Op::Store(value.clone()));
+                Ok(())
+            }
+            None => self.store.store(key, value).await,
+        }
+    }
+
+    pub(crate) async fn delete(&self, key: &K) -> Result<(), StoreError> {
+        match &self.queue {
+            Some(queue) => {
+                queue.enqueue(key.clone(), Op::Delete);
+                Ok(())
+            }
+            None => self.store.delete(key).await,
+        }
+    }
+
+    pub(crate) async fn flush(&self) -> Result<(), StoreError> {
+        match &self.queue {
+            Some(queue) => queue.flush_all(self.store.as_ref()).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn shutdown(&self) -> Result<(), StoreError> {
+        if let Some(queue) = &self.queue {
+            queue.shutdown.notify_one();
+        }
+        self.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A store that fails the first `failures` writes and counts batch calls. Writes
+    /// and deletes wait while a test holds `gate`.
+    #[derive(Clone, Default)]
+    struct FlakyStore {
+        inner: MemoryStore<u32, u32>,
+        failures: Arc<AtomicUsize>,
+        batches: Arc<AtomicUsize>,
+        gate: Arc<AsyncMutex<()>>,
+    }
+
+    #[async_trait]
+    impl Store<u32, u32> for FlakyStore {
+        async fn load(&self, key: &u32) -> Result<Option<u32>, StoreError> {
+            self.inner.load(key).await
+        }
+
+        async fn store(&self, key: &u32, value: &u32) -> Result<(), StoreError> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err("store unavailable".into());
+            }
+            self.inner.store(key, value).await
+        }
+
+        async fn delete(&self, key: &u32) -> Result<(), StoreError> {
+            let _open = self.gate.lock().await;
+            self.inner.delete(key).await
+        }
+
+        async fn store_batch(&self, entries: &[(u32, u32)]) -> Result<(), StoreError> {
+            let _open = self.gate.lock().await;
+            self.batches.fetch_add(1, Ordering::SeqCst);
+            for (key, value) in entries {
+                self.store(key, value).await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn write_behind(store: &FlakyStore, config: WriteBehind) -> Backing<u32, u32> {
+        Backing::write_behind(Arc::new(store.clone()), config)
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_coalesced_per_key() {
+        let store = FlakyStore::default();
+        let backing = write_behind(&store, WriteBehind::new(Duration::from_secs(60)));
+
+        backing.store(&1, &10).await.unwrap();
+        backing.store(&1, &11).await.unwrap();
+        backing.store(&2, &20).await.unwrap();
+        backing.delete(&2).await.unwrap();
+
+        assert_eq!(backing.load(&1).await.unwrap(), Some(11));
+        assert_eq!(backing.load(&2).await.unwrap(), None);
+        assert!(store.inner.is_empty());
+
+        backing.flush().await.unwrap();
+
+        assert_eq!(store.inner.get(&1), Some(11));
+        assert_eq!(store.inner.get(&2), None);
+        assert_eq!(store.inner.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_writes_stay_visible() {
+        let store = FlakyStore::default();
+        store.inner.store(&2, &20).await.unwrap();
+        let backing = Arc::new(write_behind(
+            &store,
+            WriteBehind::new(Duration::from_secs(60)),
+        ));
+
+        backing.store(&1, &10).await.unwrap();
+        backing.delete(&2).await.unwrap();
+        let gate = store.gate.lock().await;
+        let flush = tokio::spawn({
+            let backing = backing.clone();
+            async move { backing.flush().await }
+        });
+        while !backing.queue.as_ref().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        // Drained but not yet acknowledged: the store still has the old state
+        assert_eq!(store.inner.get(&1), None);
+        assert_eq!(backing.load(&1).await.unwrap(), Some(10));
+        assert_eq!(backing.load(&2).await.unwrap(), None);
+
+        drop(gate);
+        flush.await.unwrap().unwrap();
+        assert_eq!(backing.load(&1).await.unwrap(), Some(10));
+        assert_eq!(backing.load(&2).await.unwrap(), None);
+        assert!(backing.queue.as_ref().unwrap().lock().in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_flush_uses_batches() {
+        let store = FlakyStore::default();
+        let backing = write_behind(
+            &store,
+            WriteBehind::new(Duration::from_secs(60)).batch_size(2),
+        );
+
+        for i in 0..5 {
+            backing.store(&i, &i).await.unwrap();
+        }
+        backing.flush().await.unwrap();
+
+        assert_eq!(store.inner.len(), 5);
+        assert_eq!(store.batches.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_writes_are_retried() {
+        let store = FlakyStore::default();
+        store.failures.store(2, Ordering::SeqCst);
+        let backing = write_behind(
+            &store,
+            WriteBehind::new(Duration::from_secs(60)).max_retries(3),
+        );
+
+        backing.store(&1, &10).await.unwrap();
+        backing.flush().await.unwrap();
+
+        assert_eq!(store.inner.get(&1), Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_dropped_after_max_retries() {
+        let store = FlakyStore::default();
+        store.failures.store(10, Ordering::SeqCst);
+        let backing = write_behind(
+            &store,
+            WriteBehind::new(Duration::from_secs(60)).max_retries(1),
+        );
+
+        backing.store(&1, &10).await.unwrap();
+
+        assert!(backing.flush().await.is_err());
+        assert!(store.inner.is_empty());
+        assert_eq!(backing.load(&1).await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_periodic_flush() {
+        let store = FlakyStore::default();
+        let backing = write_behind(&store, WriteBehind::new(Duration::from_millis(20)));
+        backing.spawn_flusher();
+
+        backing.store(&1, &10).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(store.inner.get(&1), Some(10));
+        backing.shutdown().await.unwrap();
+    }
+}